@@ -7,7 +7,7 @@ License:    MIT
 URL:        https://github.com/linux-surface/surface-control
 
 Requires:       dbus libgcc systemd-libs
-BuildRequires:  rust cargo systemd-rpm-macros systemd-devel
+BuildRequires:  rust cargo systemd-rpm-macros systemd-devel dbus-devel
 
 %global debug_package %{nil}
 
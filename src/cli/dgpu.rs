@@ -1,7 +1,10 @@
 use std::convert::TryFrom;
+use std::ffi::OsString;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant};
 
 use crate::cli::Command as DynCommand;
-use crate::sys::pci::PciDevice;
+use crate::sys::pci::{PciDevice, PowerState, RuntimePowerManagement};
 use crate::sys::Error;
 use crate::sys;
 
@@ -28,23 +31,108 @@ impl DynCommand for Command {
             .subcommand(clap::Command::new("id")
                 .alias("get-id")
                 .about("Get the dGPU PCI device ID")
+                .arg(Arg::new("pci-ids")
+                    .help("PCI ID database used to resolve names")
+                    .long("pci-ids")
+                    .value_name("FILE")
+                    .value_parser(clap::value_parser!(PathBuf)))
+                .arg(Arg::new("json")
+                    .help("Print as JSON")
+                    .long("json")
+                    .action(clap::ArgAction::SetTrue))
                 .display_order(1))
             .subcommand(clap::Command::new("get-power-state")
                 .aliases(["ps", "get-ps", "power-state"])
                 .about("Get the dGPU PCI power state")
+                .arg(Arg::new("verbose")
+                    .help("Include the driver's runtime D3 report, if available")
+                    .short('v')
+                    .long("verbose")
+                    .action(clap::ArgAction::SetTrue))
                 .display_order(2))
             .subcommand(clap::Command::new("get-runtime-pm")
                 .aliases(["rpm", "get-rpm"])
                 .about("Get the dGPU runtime PM control")
+                .arg(Arg::new("verbose")
+                    .help("Also show the persistent rule and a per-function breakdown")
+                    .short('v')
+                    .long("verbose")
+                    .action(clap::ArgAction::SetTrue))
                 .display_order(3))
             .subcommand(clap::Command::new("set-runtime-pm")
                 .alias("set-rpm")
                 .about("Set the dGPU runtime PM control")
                 .arg(Arg::new("mode")
                     .value_parser(clap::value_parser!(sys::pci::RuntimePowerManagement))
-                    .required(true)
+                    .required_unless_present("unpersist")
                     .index(1))
+                .arg(Arg::new("persist")
+                    .help("Persist the mode via a udev rule")
+                    .long("persist")
+                    .action(clap::ArgAction::SetTrue)
+                    .conflicts_with("unpersist"))
+                .arg(Arg::new("unpersist")
+                    .help("Remove a previously persisted mode")
+                    .long("unpersist")
+                    .action(clap::ArgAction::SetTrue))
+                .arg(Arg::new("verbose")
+                    .help("Show a per-function breakdown")
+                    .short('v')
+                    .long("verbose")
+                    .action(clap::ArgAction::SetTrue))
                 .display_order(4))
+            .subcommand(clap::Command::new("run")
+                .about("Run a command on the dGPU via PRIME render offload")
+                .arg(Arg::new("wait-ready")
+                    .help("Wake the dGPU and wait for it to reach D0 before running the command")
+                    .long("wait-ready")
+                    .action(clap::ArgAction::SetTrue))
+                .arg(Arg::new("command")
+                    .help("The command to run, followed by its arguments")
+                    .value_parser(clap::value_parser!(OsString))
+                    .num_args(1..)
+                    .trailing_var_arg(true)
+                    .allow_hyphen_values(true)
+                    .required(true))
+                .display_order(5))
+            .subcommand(clap::Command::new("power")
+                .about("Control the dGPU power switch (Surface Book 1 and 2)")
+                .subcommand_required(true)
+                .arg_required_else_help(true)
+                .subcommand(clap::Command::new("get")
+                    .about("Get the dGPU power switch state"))
+                .subcommand(clap::Command::new("on")
+                    .about("Power on the dGPU"))
+                .subcommand(clap::Command::new("off")
+                    .about("Power off the dGPU"))
+                .display_order(6))
+            .subcommand(clap::Command::new("sensors")
+                .about("Show dGPU temperature, fan, power and memory usage")
+                .display_order(7))
+            .subcommand(clap::Command::new("bench-wake")
+                .about("Measure the dGPU wake-up latency from runtime suspend")
+                .arg(Arg::new("iterations")
+                    .help("Number of suspend/wake rounds")
+                    .short('n')
+                    .long("iterations")
+                    .value_name("N")
+                    .value_parser(clap::value_parser!(u32).range(1..))
+                    .default_value("10"))
+                .arg(Arg::new("method")
+                    .help("How to wake the dGPU")
+                    .long("method")
+                    .value_parser(["config", "runtime-pm"])
+                    .default_value("config"))
+                .arg(Arg::new("suspend-timeout")
+                    .help("Maximum time in seconds to wait for the dGPU to suspend")
+                    .long("suspend-timeout")
+                    .value_name("SECS")
+                    .value_parser(clap::value_parser!(u64))
+                    .default_value("30"))
+                .display_order(8))
+            .subcommand(clap::Command::new("why-awake")
+                .about("Diagnose why the dGPU is not suspending")
+                .display_order(9))
     }
 
     fn execute(&self, m: &clap::ArgMatches) -> Result<()> {
@@ -53,6 +141,11 @@ impl DynCommand for Command {
             Some(("get-power-state", m)) => self.get_power_state(m),
             Some(("get-runtime-pm",  m)) => self.get_runtime_pm(m),
             Some(("set-runtime-pm",  m)) => self.set_runtime_pm(m),
+            Some(("run",             m)) => self.run(m),
+            Some(("power",           m)) => self.power(m),
+            Some(("sensors",         m)) => self.sensors(m),
+            Some(("bench-wake",      m)) => self.bench_wake(m),
+            Some(("why-awake",       m)) => self.why_awake(m),
             _                            => unreachable!(),
         }
     }
@@ -70,9 +163,39 @@ impl Command {
         let device_id = dgpu.device_id()
             .context("Failed to get device ID")?;
 
-        if !m.get_flag("quiet") {
-            println!("Vendor: {vendor_id:04x}");
-            println!("Device: {device_id:04x}");
+        let subsystem = dgpu.subsystem_vendor_id().ok()
+            .zip(dgpu.subsystem_device_id().ok());
+
+        // Names are purely informational, don't fail if we can't resolve them.
+        let pci_ids: Option<&PathBuf> = m.get_one("pci-ids");
+        let names = lookup_dgpu_names(&dgpu, pci_ids.map(|p| p.as_path()))
+            .unwrap_or_default();
+
+        if m.get_flag("json") {
+            let id = PrettyId { vendor_id, device_id, subsystem, names };
+
+            let text = serde_json::to_string(&id)
+                .context("Failed to serialize data")?;
+
+            println!("{text}");
+
+        } else if !m.get_flag("quiet") {
+            let name = |n: &Option<String>| n.as_ref().map(|n| format!(" ({n})")).unwrap_or_default();
+
+            println!("Vendor:    {vendor_id:04x}{}", name(&names.vendor));
+            println!("Device:    {device_id:04x}{}", name(&names.device));
+
+            if let Some((sv, sd)) = subsystem {
+                let sname = match (&names.subsystem_vendor, &names.subsystem) {
+                    (Some(v), Some(d)) => Some(format!("{v} {d}")),
+                    (Some(v), None)    => Some(v.clone()),
+                    (None, Some(d))    => Some(d.clone()),
+                    (None, None)       => None,
+                };
+
+                println!("Subsystem: {sv:04x}:{sd:04x}{}", name(&sname));
+            }
+
         } else {
             println!("{vendor_id:04x}:{device_id:04x}");
         }
@@ -80,7 +203,7 @@ impl Command {
         Ok(())
     }
 
-    fn get_power_state(&self, _m: &clap::ArgMatches) -> Result<()> {
+    fn get_power_state(&self, m: &clap::ArgMatches) -> Result<()> {
         let dgpu = find_dgpu_device()
             .context("Failed to look up discrete GPU device")?
             .ok_or_else(|| anyhow::anyhow!("No discrete GPU found"))?;
@@ -88,40 +211,566 @@ impl Command {
         let pstate = dgpu.get_power_state()
             .context("Failed to get device power state")?;
 
-         println!("{pstate}");
+        if !m.get_flag("verbose") {
+            println!("{pstate}");
+            return Ok(());
+        }
+
+        let report = sys::nvidia::PowerReport::load(&dgpu)
+            .context("Failed to read NVIDIA driver power report")?;
+
+        println!("Power State:  {pstate}");
+
+        if let Some(report) = report {
+            if let Some(status) = &report.runtime_d3_status {
+                println!("Runtime D3:   {status}");
+            }
+            if let Some(vmem) = &report.video_memory {
+                println!("Video Memory: {vmem}");
+            }
+        }
 
         Ok(())
     }
 
-    fn get_runtime_pm(&self, _m: &clap::ArgMatches) -> Result<()> {
-        let dgpu = find_dgpu_device()
+    fn get_runtime_pm(&self, m: &clap::ArgMatches) -> Result<()> {
+        let functions = find_dgpu_functions()
             .context("Failed to look up discrete GPU device")?
             .ok_or_else(|| anyhow::anyhow!("No discrete GPU found"))?;
 
-        let mode = dgpu.get_runtime_pm()
-            .context("Failed to get runtime PM mode")?;
+        let mut modes = Vec::with_capacity(functions.len());
+        for function in &functions {
+            let mode = function.get_runtime_pm()
+                .with_context(|| format!("Failed to get runtime PM mode of {:?}", function.address()))?;
+
+            modes.push(mode);
+        }
+
+        let summary = if modes.iter().all(|m| *m == modes[0]) {
+            modes[0].to_string()
+        } else {
+            "Mixed".to_string()
+        };
+
+        if m.get_flag("quiet") || !m.get_flag("verbose") {
+            println!("{summary}");
+            return Ok(());
+        }
 
-        println!("{mode}");
+        let rule = sys::rules::RuleFile::new(RUNTIME_PM_RULE);
+
+        println!("Runtime PM: {summary}");
+        if rule.exists() {
+            println!("Persistent: yes ({})", rule.path().display());
+        } else {
+            println!("Persistent: no");
+        }
+
+        println!("Functions:");
+
+        for (function, mode) in functions.iter().zip(modes) {
+            let class = function.class()
+                .context("Failed to get device class")?;
+
+            println!("  {} [{class:x}]: {mode}", function.address().to_string_lossy());
+        }
 
         Ok(())
     }
 
     fn set_runtime_pm(&self, m: &clap::ArgMatches) -> Result<()> {
-        let mode: sys::pci::RuntimePowerManagement = *m.get_one("mode").unwrap();
+        let quiet = m.get_flag("quiet");
+        let verbose = m.get_flag("verbose") && !quiet;
+        let rule = sys::rules::RuleFile::new(RUNTIME_PM_RULE);
+
+        if m.get_flag("unpersist") {
+            let removed = rule.remove()
+                .context("Failed to remove udev rule")?;
+
+            if removed {
+                sys::rules::reload()
+                    .context("Failed to reload udev rules")?;
+            }
+
+            if !quiet && removed {
+                println!("Removed persistent runtime PM rule '{}'", rule.path().display());
+            } else if !quiet {
+                println!("No persistent runtime PM rule found");
+            }
+        }
+
+        let mode: RuntimePowerManagement = match m.get_one("mode") {
+            Some(mode) => *mode,
+            None => return Ok(()),
+        };
+
+        let mut functions = find_dgpu_functions()
+            .context("Failed to look up discrete GPU device")?
+            .ok_or_else(|| anyhow::anyhow!("No discrete GPU found"))?;
+
+        for function in &mut functions {
+            function.set_runtime_pm(mode)
+                .with_context(|| format!("Failed to set runtime PM mode of {:?}", function.address()))?;
+
+            if verbose {
+                println!("  {}: {mode}", function.address().to_string_lossy());
+            }
+        }
+
+        if !quiet {
+            println!("Discrete GPU runtime PM set to '{mode}'");
+        }
+
+        if m.get_flag("persist") {
+            let rules = functions.iter()
+                .map(|f| f.runtime_pm_rule(mode))
+                .collect::<std::result::Result<Vec<_>, _>>()
+                .context("Failed to generate udev rule")?;
+
+            rule.write(&rules)
+                .context("Failed to write udev rule")?;
+
+            sys::rules::reload()
+                .context("Failed to reload udev rules")?;
+
+            if !quiet {
+                println!("Persistent runtime PM rule written to '{}'", rule.path().display());
+            }
+        }
+
+        Ok(())
+    }
+
+    fn run(&self, m: &clap::ArgMatches) -> Result<()> {
+        let command: Vec<&OsString> = m.get_many("command").unwrap().collect();
 
         let mut dgpu = find_dgpu_device()
             .context("Failed to look up discrete GPU device")?
             .ok_or_else(|| anyhow::anyhow!("No discrete GPU found"))?;
 
-        dgpu.set_runtime_pm(mode)
-            .context("Failed to set runtime PM mode")?;
+        let env = offload_env(&dgpu)?;
+
+        let restore = if m.get_flag("wait-ready") {
+            // Make sure we get to restore the runtime PM mode when
+            // interrupted. Signals are passed on to the command instead.
+            sys::signal::catch_termination()
+                .context("Failed to set up signal handler")?;
+
+            Some(wake(&mut dgpu)?)
+        } else {
+            None
+        };
+
+        let status = std::process::Command::new(command[0])
+            .args(&command[1..])
+            .envs(env)
+            .spawn()
+            .with_context(|| format!("Failed to run command {:?}", command[0]))
+            .and_then(|mut child| crate::cli::wait_child(&mut child));
+
+        if let Some(mode) = restore {
+            dgpu.set_runtime_pm(mode)
+                .context("Failed to restore runtime PM mode")?;
+        }
+
+        std::process::exit(crate::cli::exit_code(status?))
+    }
+
+    fn power(&self, m: &clap::ArgMatches) -> Result<()> {
+        use sys::dgpu_sw::PowerSwitch;
+
+        let dev = sys::dgpu_sw::Device::open()
+            .context("Failed to open dGPU power switch (only available on Surface Book 1 and 2)")?;
+
+        let state = match m.subcommand() {
+            Some(("get", _)) => {
+                let state = dev.get()
+                    .context("Failed to get dGPU power switch state")?;
+
+                match state {
+                    Some(state) => println!("{state}"),
+                    None => anyhow::bail!("dGPU power switch state cannot be read back on this device"),
+                }
+
+                return Ok(());
+            },
+            Some(("on",  _)) => PowerSwitch::On,
+            Some(("off", _)) => PowerSwitch::Off,
+            _                => unreachable!(),
+        };
+
+        dev.set(state)
+            .context("Failed to set dGPU power switch state")?;
 
         if !m.get_flag("quiet") {
-            println!("Discrete GPU runtime PM set to '{mode}'");
+            println!("Discrete GPU power switch set to '{state}'");
+        }
+
+        Ok(())
+    }
+
+    fn sensors(&self, m: &clap::ArgMatches) -> Result<()> {
+        let dgpu = find_dgpu_device()
+            .context("Failed to look up discrete GPU device")?
+            .ok_or_else(|| anyhow::anyhow!("No discrete GPU found"))?;
+
+        let pstate = dgpu.get_power_state()
+            .context("Failed to get device power state")?;
+
+        // Reading sensors may wake up the device, so only do that if it is
+        // already active.
+        let sensors = if pstate == PowerState::D0 {
+            Some(sys::sensors::Sensors::load(&dgpu).context("Failed to read sensors")?)
+        } else {
+            None
+        };
+
+        if m.get_flag("quiet") {
+            let text = serde_json::to_string(&PrettySensors(sensors))
+                .context("Failed to serialize data")?;
+
+            println!("{text}");
+            return Ok(());
+        }
+
+        let sensors = match sensors {
+            Some(sensors) => sensors,
+            None => {
+                println!("suspended");
+                return Ok(());
+            },
+        };
+
+        if let Some(temp) = sensors.temperature {
+            println!("Temperature:  {temp:.1} °C");
+        }
+        if let Some(fan) = sensors.fan {
+            println!("Fan:          {fan} RPM");
+        }
+        if let Some(power) = sensors.power {
+            println!("Power:        {power:.2} W");
+        }
+        if let Some(used) = sensors.vram_used {
+            print!("VRAM:         {} MiB", used / (1024 * 1024));
+            if let Some(total) = sensors.vram_total {
+                print!(" / {} MiB", total / (1024 * 1024));
+            }
+            println!();
+        }
+
+        Ok(())
+    }
+
+    fn bench_wake(&self, m: &clap::ArgMatches) -> Result<()> {
+        let iterations: u32 = *m.get_one("iterations").unwrap();
+        let method: &String = m.get_one("method").unwrap();
+        let suspend_timeout = Duration::from_secs(*m.get_one("suspend-timeout").unwrap());
+        let quiet = m.get_flag("quiet");
+
+        let mut dgpu = find_dgpu_device()
+            .context("Failed to look up discrete GPU device")?
+            .ok_or_else(|| anyhow::anyhow!("No discrete GPU found"))?;
+
+        let original = dgpu.get_runtime_pm()
+            .context("Failed to get runtime PM mode")?;
+
+        // Make sure we get to restore the runtime PM mode when interrupted.
+        sys::signal::catch_termination()
+            .context("Failed to set up signal handler")?;
+
+        let mut samples = Vec::with_capacity(iterations as usize);
+        let result = (0..iterations).try_for_each(|i| {
+            let (from, latency) = bench_wake_round(&mut dgpu, method, suspend_timeout)?;
+
+            if !quiet {
+                println!("Round {:>3}: {} -> D0 in {:.1} ms", i + 1, from, latency.as_secs_f64() * 1e3);
+            }
+
+            samples.push(latency);
+            Ok::<_, anyhow::Error>(())
+        });
+
+        dgpu.set_runtime_pm(original)
+            .context("Failed to restore runtime PM mode")?;
+
+        result?;
+
+        let stats = BenchStats::new(samples);
+
+        if !quiet {
+            println!();
+            println!("Min:    {:.1} ms", stats.min.as_secs_f64() * 1e3);
+            println!("Median: {:.1} ms", stats.median.as_secs_f64() * 1e3);
+            println!("P95:    {:.1} ms", stats.p95.as_secs_f64() * 1e3);
+
+        } else {
+            let text = serde_json::to_string(&stats)
+                .context("Failed to serialize data")?;
+
+            println!("{text}");
         }
 
         Ok(())
     }
+
+    fn why_awake(&self, m: &clap::ArgMatches) -> Result<()> {
+        let dgpu = find_dgpu_device()
+            .context("Failed to look up discrete GPU device")?
+            .ok_or_else(|| anyhow::anyhow!("No discrete GPU found"))?;
+
+        let pstate = dgpu.get_power_state()
+            .context("Failed to get device power state")?;
+
+        let findings = diagnose_awake(&dgpu)?;
+
+        if m.get_flag("quiet") {
+            let findings: Vec<_> = findings.iter()
+                .map(|f| serde_json::json!({ "reason": f.reason, "fix": f.fix }))
+                .collect();
+
+            let text = serde_json::to_string(&serde_json::json!({
+                "power-state": pstate.to_string(),
+                "findings": findings,
+            })).context("Failed to serialize data")?;
+
+            println!("{text}");
+            return Ok(());
+        }
+
+        println!("Power State: {pstate}");
+
+        if findings.is_empty() {
+            if pstate == PowerState::D0 {
+                println!("No obvious reason found why the discrete GPU is awake");
+            }
+            return Ok(());
+        }
+
+        for (i, finding) in findings.iter().enumerate() {
+            println!();
+            println!("{}. {}", i + 1, finding.reason);
+            println!("   Fix: {}", finding.fix);
+        }
+
+        Ok(())
+    }
+}
+
+/// A likely reason for the dGPU not suspending.
+struct Finding {
+    /// Lower ranks are more likely to be the root cause.
+    rank: u8,
+    reason: String,
+    fix: String,
+}
+
+fn diagnose_awake(dgpu: &PciDevice) -> Result<Vec<Finding>> {
+    let functions = dgpu.slot_functions()
+        .context("Failed to look up discrete GPU functions")?;
+
+    let mut findings = Vec::new();
+
+    for function in &functions {
+        let address = function.address().to_string_lossy();
+
+        let rpm = function.get_runtime_pm().ok();
+        if rpm == Some(RuntimePowerManagement::Off) {
+            findings.push(Finding {
+                rank: 0,
+                reason: format!("Runtime PM is disabled for {address}"),
+                fix: "Run 'surface dgpu set-runtime-pm on'".into(),
+            });
+        }
+
+        if function.get_runtime_disabled().ok() == Some(true) {
+            let driver = function.driver()
+                .map(|d| d.to_string_lossy().into_owned())
+                .unwrap_or_else(|| "no driver".into());
+
+            findings.push(Finding {
+                rank: 1,
+                reason: format!("Runtime PM is not enabled by the kernel for {address} ({driver})"),
+                fix: "Use a driver with runtime PM support for this function".into(),
+            });
+        }
+
+        let displays = function.connected_displays()
+            .context("Failed to look up display connectors")?;
+
+        if !displays.is_empty() {
+            findings.push(Finding {
+                rank: 3,
+                reason: format!("Display connected to the discrete GPU: {}", displays.join(", ")),
+                fix: "Disconnect the display or connect it to a port driven by the integrated GPU".into(),
+            });
+        }
+
+        // With runtime PM disabled, the usage counter is always elevated.
+        let usage = function.get_runtime_usage().unwrap_or(0);
+        if usage > 0 && rpm != Some(RuntimePowerManagement::Off) {
+            findings.push(Finding {
+                rank: 5,
+                reason: format!("{address} holds {usage} runtime PM reference(s)"),
+                fix: "Close applications using the device or check the driver for leaked references".into(),
+            });
+        }
+    }
+
+    let report = sys::nvidia::PowerReport::load(dgpu)
+        .context("Failed to read NVIDIA driver power report")?;
+
+    if let Some(status) = report.and_then(|r| r.runtime_d3_status) {
+        if !status.starts_with("Enabled") {
+            findings.push(Finding {
+                rank: 1,
+                reason: format!("NVIDIA driver runtime D3 status: {status}"),
+                fix: "Load the nvidia module with 'NVreg_DynamicPowerManagement=0x02'".into(),
+            });
+        }
+    }
+
+    let mut device = Some(dgpu.reload()?);
+    while let Some(dev) = device {
+        if dev.get_d3cold_allowed().ok() == Some(false) {
+            findings.push(Finding {
+                rank: 2,
+                reason: format!("D3cold is not allowed for {}", dev.address().to_string_lossy()),
+                fix: format!("Run 'echo 1 > {}'", dev.base().syspath().join("d3cold_allowed").display()),
+            });
+        }
+
+        device = dev.parent();
+    }
+
+    let nodes = find_dgpu_device_nodes(dgpu)
+        .context("Failed to look up device nodes")?;
+
+    let processes = sys::proc::processes_using(&nodes)
+        .context("Failed to look up processes")?;
+
+    if !processes.is_empty() {
+        let processes: Vec<_> = processes.iter().map(|p| p.to_string()).collect();
+
+        findings.push(Finding {
+            rank: 4,
+            reason: format!("Processes holding discrete GPU device nodes: {}", processes.join(", ")),
+            fix: "Close these applications or run them on the integrated GPU".into(),
+        });
+    }
+
+    findings.sort_by_key(|f| f.rank);
+    Ok(findings)
+}
+
+const BENCH_POLL_INTERVAL: Duration = Duration::from_millis(1);
+
+/// Let the device runtime-suspend, wake it up and measure the time until it
+/// reaches D0. Returns the suspended state and the wake-up latency.
+fn bench_wake_round(dgpu: &mut PciDevice, method: &str, suspend_timeout: Duration) -> Result<(PowerState, Duration)> {
+    dgpu.set_runtime_pm(RuntimePowerManagement::On)
+        .context("Failed to set runtime PM mode")?;
+
+    let start = Instant::now();
+    let from = loop {
+        let state = dgpu.reload()?.get_power_state()
+            .context("Failed to get device power state")?;
+
+        if matches!(state, PowerState::D3hot | PowerState::D3cold) {
+            break state;
+        }
+
+        if start.elapsed() >= suspend_timeout {
+            anyhow::bail!("Discrete GPU did not suspend, check 'surface dgpu get-runtime-pm -v'");
+        }
+
+        if let Some(signal) = sys::signal::received() {
+            anyhow::bail!("Interrupted by {signal}");
+        }
+
+        std::thread::sleep(BENCH_POLL_INTERVAL);
+    };
+
+    let start = Instant::now();
+
+    match method {
+        "config" => dgpu.read_config(&mut [0; 4])
+            .context("Failed to read PCI config space")?,
+        "runtime-pm" => dgpu.set_runtime_pm(RuntimePowerManagement::Off)
+            .context("Failed to set runtime PM mode")?,
+        _ => unreachable!(),
+    }
+
+    loop {
+        let state = dgpu.reload()?.get_power_state()
+            .context("Failed to get device power state")?;
+
+        if state == PowerState::D0 {
+            break;
+        }
+
+        if start.elapsed() >= WAKE_TIMEOUT {
+            anyhow::bail!("Timed out waiting for discrete GPU to reach D0");
+        }
+
+        if let Some(signal) = sys::signal::received() {
+            anyhow::bail!("Interrupted by {signal}");
+        }
+
+        std::thread::sleep(BENCH_POLL_INTERVAL);
+    }
+
+    Ok((from, start.elapsed()))
+}
+
+const WAKE_TIMEOUT: Duration = Duration::from_secs(5);
+
+const RUNTIME_PM_RULE: &str = "80-surface-dgpu-runtime-pm.rules";
+
+/// Build the environment for PRIME render offload to the given device.
+fn offload_env(dgpu: &PciDevice) -> Result<Vec<(&'static str, String)>> {
+    let driver = dgpu.driver()
+        .ok_or_else(|| anyhow::anyhow!("No driver bound to discrete GPU"))?;
+
+    if driver == sys::nvidia::DRIVER_NAME {
+        return Ok(vec![
+            ("__NV_PRIME_RENDER_OFFLOAD", "1".into()),
+            ("__GLX_VENDOR_LIBRARY_NAME", "nvidia".into()),
+            ("__VK_LAYER_NV_optimus", "NVIDIA_only".into()),
+        ]);
+    }
+
+    let node = dgpu.render_node()
+        .context("Failed to look up render node")?
+        .ok_or_else(|| anyhow::anyhow!("No render node found for discrete GPU"))?;
+
+    let tag = node.property_value("ID_PATH_TAG")
+        .map(|t| t.to_string_lossy().into_owned())
+        .unwrap_or_else(|| dgpu.path_tag());
+
+    Ok(vec![("DRI_PRIME", tag)])
+}
+
+/// Disable runtime PM and wait for the device to reach D0.
+///
+/// Returns the previous runtime PM mode, which should be restored afterwards.
+fn wake(dgpu: &mut PciDevice) -> Result<RuntimePowerManagement> {
+    let mode = dgpu.get_runtime_pm()
+        .context("Failed to get runtime PM mode")?;
+
+    dgpu.set_runtime_pm(RuntimePowerManagement::Off)
+        .context("Failed to set runtime PM mode")?;
+
+    let ready = dgpu.wait_for_power_state(PowerState::D0, WAKE_TIMEOUT)
+        .context("Failed to get device power state")?;
+
+    if !ready {
+        dgpu.set_runtime_pm(mode)
+            .context("Failed to restore runtime PM mode")?;
+
+        anyhow::bail!("Timed out waiting for discrete GPU to reach D0");
+    }
+
+    Ok(mode)
 }
 
 pub fn find_dgpu_device() -> crate::sys::Result<Option<PciDevice>> {
@@ -158,7 +807,52 @@ pub fn find_dgpu_device() -> crate::sys::Result<Option<PciDevice>> {
     Ok(None)
 }
 
-impl ValueEnum for sys::pci::RuntimePowerManagement {
+/// Resolve vendor, device and subsystem names of the given device via the PCI
+/// ID database, optionally at a non-default location.
+pub fn lookup_dgpu_names(dgpu: &PciDevice, pci_ids: Option<&Path>) -> crate::sys::Result<sys::pciids::Names> {
+    let db = match pci_ids {
+        Some(path) => sys::pciids::Database::open_path(path)?,
+        None => sys::pciids::Database::open()?,
+    };
+
+    let vendor = dgpu.vendor_id()
+        .map_err(|source| Error::SysFs { source })?;
+
+    let device = dgpu.device_id()
+        .map_err(|source| Error::SysFs { source })?;
+
+    let subsystem = dgpu.subsystem_vendor_id().ok()
+        .zip(dgpu.subsystem_device_id().ok());
+
+    db.lookup(vendor, device, subsystem)
+}
+
+/// Look up all functions in the PCI slot of the discrete GPU, e.g. HDMI
+/// audio, USB-C and UCSI controllers next to the display function.
+pub fn find_dgpu_functions() -> crate::sys::Result<Option<Vec<PciDevice>>> {
+    match find_dgpu_device()? {
+        Some(dgpu) => dgpu.slot_functions().map(Some),
+        None => Ok(None),
+    }
+}
+
+/// Collect the device nodes of all functions of the discrete GPU, including
+/// the node of the proprietary NVIDIA driver.
+pub fn find_dgpu_device_nodes(dgpu: &PciDevice) -> crate::sys::Result<Vec<PathBuf>> {
+    let mut nodes = Vec::new();
+
+    for function in dgpu.slot_functions()? {
+        nodes.extend(function.device_nodes()?);
+    }
+
+    if let Some(node) = sys::nvidia::device_node(dgpu)? {
+        nodes.push(node);
+    }
+
+    Ok(nodes)
+}
+
+impl ValueEnum for RuntimePowerManagement {
     fn value_variants<'a>() -> &'a [Self] {
         &[Self::On, Self::Off]
     }
@@ -170,3 +864,102 @@ impl ValueEnum for sys::pci::RuntimePowerManagement {
         }
     }
 }
+
+
+struct PrettyId {
+    vendor_id: u16,
+    device_id: u16,
+    subsystem: Option<(u16, u16)>,
+    names: sys::pciids::Names,
+}
+
+impl serde::Serialize for PrettyId {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: serde::Serializer
+    {
+        use serde::ser::SerializeStruct;
+
+        let mut s = serializer.serialize_struct("Id", 8)?;
+
+        s.serialize_field("vendor", &self.vendor_id)?;
+        s.serialize_field("device", &self.device_id)?;
+        s.serialize_field("subsystem-vendor", &self.subsystem.map(|(v, _)| v))?;
+        s.serialize_field("subsystem-device", &self.subsystem.map(|(_, d)| d))?;
+        s.serialize_field("vendor-name", &self.names.vendor)?;
+        s.serialize_field("device-name", &self.names.device)?;
+        s.serialize_field("subsystem-vendor-name", &self.names.subsystem_vendor)?;
+        s.serialize_field("subsystem-name", &self.names.subsystem)?;
+        s.end()
+    }
+}
+
+
+/// Wake-up latency statistics of 'bench-wake'.
+struct BenchStats {
+    iterations: usize,
+    min: Duration,
+    median: Duration,
+    p95: Duration,
+}
+
+impl BenchStats {
+    fn new(mut samples: Vec<Duration>) -> Self {
+        samples.sort();
+
+        BenchStats {
+            iterations: samples.len(),
+            min: samples[0],
+            median: samples[samples.len() / 2],
+            p95: samples[(samples.len() * 95).div_ceil(100) - 1],
+        }
+    }
+}
+
+impl serde::Serialize for BenchStats {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: serde::Serializer
+    {
+        use serde::ser::SerializeStruct;
+
+        let ms = |d: Duration| d.as_secs_f64() * 1e3;
+
+        let mut s = serializer.serialize_struct("BenchStats", 4)?;
+        s.serialize_field("iterations", &self.iterations)?;
+        s.serialize_field("min", &ms(self.min))?;
+        s.serialize_field("median", &ms(self.median))?;
+        s.serialize_field("p95", &ms(self.p95))?;
+        s.end()
+    }
+}
+
+
+struct PrettySensors(Option<sys::sensors::Sensors>);
+
+impl serde::Serialize for PrettySensors {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: serde::Serializer
+    {
+        use serde::ser::SerializeStruct;
+
+        let sensors = match &self.0 {
+            Some(sensors) => sensors,
+            None => {
+                let mut s = serializer.serialize_struct("Sensors", 1)?;
+                s.serialize_field("suspended", &true)?;
+                return s.end();
+            },
+        };
+
+        let mut s = serializer.serialize_struct("Sensors", 6)?;
+        s.serialize_field("suspended", &false)?;
+        s.serialize_field("temperature", &sensors.temperature)?;
+        s.serialize_field("fan", &sensors.fan)?;
+        s.serialize_field("power", &sensors.power)?;
+        s.serialize_field("vram-used", &sensors.vram_used)?;
+        s.serialize_field("vram-total", &sensors.vram_total)?;
+        s.end()
+    }
+}
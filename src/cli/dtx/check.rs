@@ -0,0 +1,213 @@
+use crate::cli::dtx::{base_state_str, latch_status_str};
+use crate::sys;
+use crate::sys::mounts::Mount;
+use crate::sys::proc::Process;
+
+use anyhow::{Context, Result};
+
+
+pub fn command() -> clap::Command {
+    use clap::Arg;
+
+    clap::Command::new("check")
+        .about("Check whether it is safe to detach the clipboard")
+        .arg(Arg::new("hub")
+            .help("USB hub connecting the base by sysfs name (e.g. '1-3'), instead of auto-detection")
+            .long("hub")
+            .value_name("NAME")
+            .action(clap::ArgAction::Append))
+}
+
+pub fn execute(m: &clap::ArgMatches) -> Result<()> {
+    let hubs: Vec<String> = m.get_many("hub")
+        .map(|h| h.cloned().collect())
+        .unwrap_or_default();
+
+    // Still report everything else if the device is unavailable.
+    let device = sys::dtx::open().ok();
+
+    let report = Report::collect(device.as_deref(), &hubs)?;
+
+    if !m.get_flag("quiet") {
+        print!("{report}");
+
+    } else {
+        let text = serde_json::to_string(&report.to_json())
+            .context("Failed to serialize data")?;
+
+        println!("{text}");
+    }
+
+    if !report.go() {
+        std::process::exit(1);
+    }
+
+    Ok(())
+}
+
+
+/// Read-only report of everything that would break on detachment.
+pub struct Report {
+    pub base_state: Option<sdtx::BaseState>,
+    pub latch_status: Option<sdtx::LatchStatus>,
+    pub hub_found: bool,
+    pub dgpu_processes: Vec<Process>,
+    pub mounts: Vec<Mount>,
+    pub open_handles: Vec<Process>,
+    pub audio_streams: Vec<String>,
+}
+
+impl Report {
+    pub fn collect(device: Option<&dyn sys::dtx::Backend>, hubs: &[String]) -> Result<Self> {
+        let base_state = device.and_then(|d| d.get_base_info().ok()).map(|b| b.state);
+        let latch_status = device.and_then(|d| d.get_latch_status().ok());
+
+        let dgpu = crate::cli::dgpu::find_dgpu_device()
+            .context("Failed to look up discrete GPU device")?;
+
+        let (dgpu_processes, dgpu_functions) = match &dgpu {
+            Some(dgpu) => {
+                let nodes = crate::cli::dgpu::find_dgpu_device_nodes(dgpu)
+                    .context("Failed to look up discrete GPU device nodes")?;
+
+                let processes = sys::proc::processes_using(&nodes)
+                    .context("Failed to look up processes")?;
+
+                let functions = dgpu.slot_functions()
+                    .context("Failed to look up discrete GPU functions")?;
+
+                (processes, functions)
+            },
+            None => (Vec::new(), Vec::new()),
+        };
+
+        let hubs = sys::base::find_usb_hubs(hubs)
+            .context("Failed to look up base USB hub")?;
+
+        // Without the hub, we cannot see anything in the base and would
+        // wrongly report it as safe to detach.
+        let hub_found = !hubs.is_empty();
+
+        let usb = sys::base::Base::new(hubs.clone());
+
+        let mut mounts = Vec::new();
+        for block in usb.devices(Some("block")).context("Failed to look up base block devices")? {
+            if let Some(dev) = block.attribute_value("dev") {
+                let found = sys::mounts::mounts_of(&dev.to_string_lossy())
+                    .context("Failed to read mount table")?;
+
+                mounts.extend(found);
+            }
+        }
+
+        let nodes = usb.device_nodes()
+            .context("Failed to look up base device nodes")?;
+
+        let open_handles = sys::proc::processes_using(&nodes)
+            .context("Failed to look up processes")?;
+
+        // Audio devices in the base include the HDMI audio function of the dGPU.
+        let mut roots = hubs;
+        roots.extend(dgpu_functions.iter().map(|f| f.base().clone()));
+
+        let audio_streams = sys::base::Base::new(roots)
+            .active_audio_streams()
+            .context("Failed to look up audio streams")?;
+
+        Ok(Report {
+            base_state,
+            latch_status,
+            hub_found,
+            dgpu_processes,
+            mounts,
+            open_handles,
+            audio_streams,
+        })
+    }
+
+    /// Reasons why detaching is not safe right now.
+    pub fn reasons(&self) -> Vec<String> {
+        let mut reasons = Vec::new();
+
+        match self.base_state {
+            Some(sdtx::BaseState::Attached)    => (),
+            Some(sdtx::BaseState::Detached)    => reasons.push("No base attached".into()),
+            Some(sdtx::BaseState::NotFeasible) => reasons.push("Detachment not feasible".into()),
+            None                               => reasons.push("Base state unavailable".into()),
+        }
+
+        if let Some(sdtx::LatchStatus::Error(err)) = self.latch_status {
+            reasons.push(format!("Latch error: {err}"));
+        }
+
+        if !self.hub_found && self.base_state != Some(sdtx::BaseState::Detached) {
+            reasons.push("Base USB hub not found, specify it via '--hub'".into());
+        }
+
+        for process in &self.dgpu_processes {
+            reasons.push(format!("Process using the dGPU: {process}"));
+        }
+
+        for mount in &self.mounts {
+            reasons.push(format!("Filesystem mounted: {} on {}", mount.source, mount.mountpoint.display()));
+        }
+
+        for process in &self.open_handles {
+            reasons.push(format!("Process holding a base device open: {process}"));
+        }
+
+        for card in &self.audio_streams {
+            reasons.push(format!("Audio stream active on: {card}"));
+        }
+
+        reasons
+    }
+
+    pub fn go(&self) -> bool {
+        self.reasons().is_empty()
+    }
+
+    pub fn to_json(&self) -> serde_json::Value {
+        let processes = |p: &[Process]| -> Vec<serde_json::Value> {
+            p.iter().map(|p| serde_json::json!({ "pid": p.pid, "comm": p.comm })).collect()
+        };
+
+        let mounts: Vec<_> = self.mounts.iter()
+            .map(|m| serde_json::json!({ "source": m.source, "mountpoint": m.mountpoint }))
+            .collect();
+
+        serde_json::json!({
+            "go": self.go(),
+            "reasons": self.reasons(),
+            "base-state": self.base_state.map(base_state_str),
+            "latch-status": self.latch_status.map(latch_status_str),
+            "dgpu-processes": processes(&self.dgpu_processes),
+            "mounts": mounts,
+            "open-handles": processes(&self.open_handles),
+            "audio-streams": self.audio_streams,
+        })
+    }
+}
+
+impl std::fmt::Display for Report {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        if let Some(base_state) = self.base_state {
+            writeln!(f, "Base State:   {base_state}")?;
+        }
+        if let Some(latch_status) = self.latch_status {
+            writeln!(f, "Latch Status: {latch_status}")?;
+        }
+
+        let reasons = self.reasons();
+        if reasons.is_empty() {
+            return writeln!(f, "Result:       GO");
+        }
+
+        writeln!(f, "Result:       NO-GO")?;
+        for reason in reasons {
+            writeln!(f, "  - {reason}")?;
+        }
+
+        Ok(())
+    }
+}
@@ -0,0 +1,225 @@
+use std::process::{Child, ExitStatus};
+use std::time::{Duration, Instant};
+
+use crate::cli::dtx::{cancel_reason_str, send_heartbeat, HEARTBEAT_INTERVAL};
+use crate::cli::dtx::events::{self, EventReceiver, Received};
+use crate::cli::dtx::handshake::{self, Phase, Tracker};
+use crate::sys;
+
+use anyhow::{Context, Result};
+
+
+const CHECK_POLL_INTERVAL: Duration = Duration::from_millis(100);
+
+
+pub fn command() -> clap::Command {
+    use clap::Arg;
+
+    clap::Command::new("detach")
+        .about("Detach the clipboard, running pre-detach checks before confirming")
+        .arg(Arg::new("check")
+            .help("Command to run before confirming, cancels detachment if it fails (can be repeated)")
+            .long("check")
+            .value_name("CMD")
+            .action(clap::ArgAction::Append))
+        .arg(Arg::new("timeout")
+            .help("Maximum time in seconds to wait for a response of the EC")
+            .long("timeout")
+            .value_name("SECS")
+            .value_parser(clap::value_parser!(u64))
+            .default_value("10"))
+        .arg(Arg::new("check-timeout")
+            .help("Maximum time in seconds for all checks to complete")
+            .long("check-timeout")
+            .value_name("SECS")
+            .value_parser(clap::value_parser!(u64))
+            .default_value("60"))
+        .arg(events::reconnect_arg())
+}
+
+pub fn execute(m: &clap::ArgMatches) -> Result<()> {
+    let device = sys::dtx::open()
+        .context("Failed to open DTX device")?;
+
+    run(&*device, m)
+}
+
+pub fn run(device: &dyn sys::dtx::Backend, m: &clap::ArgMatches) -> Result<()> {
+    let quiet = m.get_flag("quiet");
+    let reconnect = m.get_flag("reconnect");
+    let timeout = Duration::from_secs(*m.get_one("timeout").unwrap());
+    let check_timeout = Duration::from_secs(*m.get_one("check-timeout").unwrap());
+    let checks: Vec<&String> = m.get_many("check")
+        .map(|c| c.collect())
+        .unwrap_or_default();
+
+    // Set up the event stream first so that we don't miss any responses.
+    let events = EventReceiver::open(device)?
+        .reconnecting(reconnect);
+
+    let mut tracker = Tracker::from_device(device)?;
+
+    device.latch_request()
+        .context("Failed to send latch request")?;
+
+    let deadline = Instant::now() + timeout;
+    if !wait_for(&events, &mut tracker, &Phase::Requested, deadline)? {
+        // The request may still reach the EC, don't leave it pending.
+        device.latch_cancel()
+            .context("Failed to cancel detachment")?;
+
+        anyhow::bail!("Timed out waiting for detachment request to be acknowledged, detachment canceled");
+    }
+
+    if !quiet {
+        println!("Detachment requested");
+    }
+
+    let deadline = Instant::now() + check_timeout;
+    for check in checks {
+        if !quiet {
+            println!("Running check '{check}'");
+        }
+
+        let outcome = run_check(&events, check, deadline, || send_heartbeat(device, reconnect))?;
+
+        let error = match outcome {
+            CheckOutcome::Passed => continue,
+            CheckOutcome::Failed(status) => format!("Check '{check}' failed ({status})"),
+            CheckOutcome::TimedOut => format!("Check '{check}' timed out"),
+            CheckOutcome::Canceled(reason) => anyhow::bail!("Detachment canceled: {reason}"),
+        };
+
+        device.latch_cancel()
+            .context("Failed to cancel detachment")?;
+
+        anyhow::bail!("{error}, detachment canceled");
+    }
+
+    handshake::confirm(device)?;
+    tracker.confirm();
+
+    let deadline = Instant::now() + timeout;
+    if !wait_for(&events, &mut tracker, &Phase::Opened, deadline)? {
+        anyhow::bail!("Timed out waiting for latch to open");
+    }
+
+    if !quiet {
+        println!("Latch opened, clipboard can be detached");
+    }
+
+    Ok(())
+}
+
+
+/// Wait until the handshake reaches the given phase. Returns `false` if the
+/// deadline has passed before that.
+fn wait_for(events: &EventReceiver, tracker: &mut Tracker, phase: &Phase, deadline: Instant) -> Result<bool> {
+    while tracker.phase() != phase {
+        match events.recv_any_deadline(deadline)? {
+            Some(Received::Event(event))          => tracker.update(&event),
+            Some(Received::Reconnected(snapshot)) => tracker.resync(snapshot.latch),
+            None => return Ok(false),
+        };
+
+        if let Phase::Canceled(reason) = tracker.phase() {
+            anyhow::bail!("Detachment canceled: {reason}");
+        }
+    }
+
+    Ok(true)
+}
+
+
+enum CheckOutcome {
+    Passed,
+    Failed(ExitStatus),
+    TimedOut,
+    Canceled(String),
+}
+
+/// Run a single check, sending heartbeats to the EC while it runs.
+fn run_check<F>(events: &EventReceiver, check: &str, deadline: Instant, heartbeat: F) -> Result<CheckOutcome>
+where
+    F: Fn() -> Result<()>,
+{
+    use sdtx::Event;
+
+    let mut child = std::process::Command::new("sh")
+        .args(["-c", check])
+        .spawn()
+        .with_context(|| format!("Failed to run check '{check}'"))?;
+
+    let mut last_heartbeat = Instant::now();
+
+    loop {
+        if let Some(status) = child.try_wait().context("Failed to wait for check")? {
+            return if status.success() {
+                Ok(CheckOutcome::Passed)
+            } else {
+                Ok(CheckOutcome::Failed(status))
+            };
+        }
+
+        if Instant::now() >= deadline {
+            kill(&mut child);
+            return Ok(CheckOutcome::TimedOut);
+        }
+
+        if let Some(Event::Cancel { reason }) = events.recv_timeout(CHECK_POLL_INTERVAL)? {
+            kill(&mut child);
+            return Ok(CheckOutcome::Canceled(cancel_reason_str(&reason)));
+        }
+
+        if last_heartbeat.elapsed() >= HEARTBEAT_INTERVAL {
+            heartbeat()?;
+            last_heartbeat = Instant::now();
+        }
+    }
+}
+
+fn kill(child: &mut Child) {
+    let _ = child.kill();
+    let _ = child.wait();
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cli::dtx::matches;
+    use crate::sys::dtx::{Backend, Mock};
+
+    #[test]
+    fn detach() {
+        let mock = Mock::new();
+
+        run(&mock, &matches(&["detach", "-q", "--timeout", "1"])).unwrap();
+
+        assert_eq!(mock.get_latch_status().unwrap(), sdtx::LatchStatus::Opened);
+    }
+
+    #[test]
+    fn failed_check_cancels() {
+        let mock = Mock::new();
+
+        let err = run(&mock, &matches(&["detach", "-q", "--check", "false"])).unwrap_err();
+
+        assert!(err.to_string().contains("Check 'false' failed"), "{:#}", err);
+        assert_eq!(mock.get_latch_status().unwrap(), sdtx::LatchStatus::Closed);
+
+        // The request must not be pending anymore, i.e. a new one succeeds.
+        run(&mock, &matches(&["detach", "-q", "--timeout", "1"])).unwrap();
+    }
+
+    #[test]
+    fn not_feasible() {
+        let mock = Mock::new();
+        mock.latch_lock().unwrap();
+
+        let err = run(&mock, &matches(&["detach", "-q", "--timeout", "1"])).unwrap_err();
+
+        assert!(err.to_string().contains("Detachment canceled"), "{:#}", err);
+        assert_eq!(mock.get_latch_status().unwrap(), sdtx::LatchStatus::Closed);
+    }
+}
@@ -0,0 +1,258 @@
+use std::cell::RefCell;
+use std::time::{Duration, Instant};
+
+use crate::sys;
+
+use anyhow::{Context, Result};
+
+
+/// Initial delay before trying to reopen the device after losing it.
+const RECONNECT_DELAY_MIN: Duration = Duration::from_millis(100);
+
+/// Maximum delay between attempts to reopen the device.
+const RECONNECT_DELAY_MAX: Duration = Duration::from_secs(5);
+
+
+/// Option to reconnect to the device when losing it, for long-running modes.
+pub fn reconnect_arg() -> clap::Arg {
+    clap::Arg::new("reconnect")
+        .help("Reopen the device with backoff if it goes away, e.g. on resume or module reload")
+        .long("reconnect")
+        .action(clap::ArgAction::SetTrue)
+}
+
+
+/// State of the device after reconnecting to it.
+#[derive(Debug, Clone, Copy)]
+pub struct Snapshot {
+    pub base: sdtx::BaseInfo,
+    pub mode: sdtx::DeviceMode,
+    pub latch: sdtx::LatchStatus,
+}
+
+/// Item received from an [`EventReceiver`].
+pub enum Received {
+    Event(sdtx::Event),
+
+    /// The device has been reopened after losing it. Events may have been
+    /// missed in between, so consumers should resync from the given state.
+    Reconnected(Snapshot),
+}
+
+
+/// DTX event stream that can be waited on with a timeout.
+pub struct EventReceiver<'a> {
+    device: &'a dyn sys::dtx::Backend,
+    reconnect: bool,
+    state: RefCell<State>,
+}
+
+struct State {
+    rx: Option<sys::dtx::Events>,
+    delay: Duration,
+    retry: Instant,
+}
+
+impl<'a> EventReceiver<'a> {
+    pub fn open(device: &'a dyn sys::dtx::Backend) -> Result<Self> {
+        let rx = device.events()
+            .context("Failed to set up event stream")?;
+
+        let state = State {
+            rx: Some(rx),
+            delay: RECONNECT_DELAY_MIN,
+            retry: Instant::now(),
+        };
+
+        Ok(EventReceiver { device, reconnect: false, state: RefCell::new(state) })
+    }
+
+    /// Reopen the device if the event stream fails instead of returning the
+    /// error. This is reported via [`Received::Reconnected`].
+    pub fn reconnecting(mut self, reconnect: bool) -> Self {
+        self.reconnect = reconnect;
+        self
+    }
+
+    /// Wait for the next event. Returns `None` on timeout.
+    pub fn recv_timeout(&self, timeout: Duration) -> Result<Option<sdtx::Event>> {
+        self.recv_deadline(Instant::now() + timeout)
+    }
+
+    /// Wait for the next event. Returns `None` if the deadline has passed.
+    pub fn recv_deadline(&self, deadline: Instant) -> Result<Option<sdtx::Event>> {
+        loop {
+            match self.recv_any_deadline(deadline)? {
+                Some(Received::Event(event)) => return Ok(Some(event)),
+                Some(Received::Reconnected(_)) => continue,
+                None => return Ok(None),
+            }
+        }
+    }
+
+    /// Wait for the next event or reconnect.
+    pub fn recv_any(&self) -> Result<Received> {
+        loop {
+            let deadline = Instant::now() + Duration::from_secs(3600);
+
+            if let Some(received) = self.recv_any_deadline(deadline)? {
+                return Ok(received);
+            }
+        }
+    }
+
+    /// Wait for the next event or reconnect. Returns `None` on timeout.
+    pub fn recv_any_timeout(&self, timeout: Duration) -> Result<Option<Received>> {
+        self.recv_any_deadline(Instant::now() + timeout)
+    }
+
+    /// Wait for the next event or reconnect. Returns `None` if the deadline
+    /// has passed.
+    pub fn recv_any_deadline(&self, deadline: Instant) -> Result<Option<Received>> {
+        use std::sync::mpsc::RecvTimeoutError;
+
+        let mut state = self.state.borrow_mut();
+
+        loop {
+            let timeout = deadline.saturating_duration_since(Instant::now());
+
+            let rx = match &state.rx {
+                Some(rx) => rx,
+                None => {
+                    if Instant::now() < state.retry {
+                        if Instant::now() >= deadline {
+                            return Ok(None);
+                        }
+
+                        std::thread::sleep(state.retry.min(deadline).saturating_duration_since(Instant::now()));
+                        continue;
+                    }
+
+                    match self.reopen(&mut state) {
+                        Some(snapshot) => return Ok(Some(Received::Reconnected(snapshot))),
+                        None           => continue,
+                    }
+                },
+            };
+
+            let error = match rx.recv_timeout(timeout) {
+                Ok(Ok(event)) => return Ok(Some(Received::Event(event))),
+                Ok(Err(e)) => anyhow::Error::new(e).context("Error reading event"),
+                Err(RecvTimeoutError::Timeout) => return Ok(None),
+                Err(RecvTimeoutError::Disconnected) => anyhow::anyhow!("Event stream closed"),
+            };
+
+            if !self.reconnect {
+                return Err(error);
+            }
+
+            eprintln!("Lost DTX device, reconnecting: {error:#}");
+
+            state.rx = None;
+            state.delay = RECONNECT_DELAY_MIN;
+            state.retry = Instant::now() + state.delay;
+        }
+    }
+
+    /// Try to reopen the event stream and read the current state. On
+    /// failure, schedules the next attempt with exponential backoff.
+    fn reopen(&self, state: &mut State) -> Option<Snapshot> {
+        let result = self.device.reopen()
+            .and_then(|_| self.device.events())
+            .and_then(|rx| {
+                let snapshot = Snapshot {
+                    base: self.device.get_base_info()?,
+                    mode: self.device.get_device_mode()?,
+                    latch: self.device.get_latch_status()?,
+                };
+
+                Ok((rx, snapshot))
+            });
+
+        match result {
+            Ok((rx, snapshot)) => {
+                state.rx = Some(rx);
+                Some(snapshot)
+            },
+            Err(_) => {
+                state.delay = (state.delay * 2).min(RECONNECT_DELAY_MAX);
+                state.retry = Instant::now() + state.delay;
+                None
+            },
+        }
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cli::dtx::PrettyReconnect;
+    use crate::sys::bases::Models;
+    use crate::sys::dtx::Mock;
+
+    #[test]
+    fn error_without_reconnect() {
+        let mock = Mock::new();
+        let events = EventReceiver::open(&mock).unwrap();
+
+        mock.unplug(Duration::from_millis(100));
+
+        assert!(events.recv_any_timeout(Duration::from_secs(1)).is_err());
+    }
+
+    #[test]
+    fn reconnect() {
+        let mock = Mock::new();
+        let events = EventReceiver::open(&mock).unwrap().reconnecting(true);
+
+        // The base goes away while the device is gone, so its event is lost.
+        let start = Instant::now();
+        mock.unplug(Duration::from_secs(1));
+        mock.detach();
+
+        let snapshot = match events.recv_any_timeout(Duration::from_secs(5)).unwrap() {
+            Some(Received::Reconnected(snapshot)) => snapshot,
+            _ => panic!("expected reconnect"),
+        };
+
+        assert!(start.elapsed() >= Duration::from_secs(1));
+        assert_eq!(snapshot.base.state, sdtx::BaseState::Detached);
+        assert_eq!(snapshot.mode, sdtx::DeviceMode::Tablet);
+        assert_eq!(snapshot.latch, sdtx::LatchStatus::Closed);
+
+        // Retries after 100, 200, 400 and 800ms, not every 100ms.
+        let reopens = mock.reopens();
+        assert!((3..=5).contains(&reopens), "{} attempts", reopens);
+
+        // Events are received again afterwards.
+        mock.attach(sdtx::DeviceType::Ssh, 0x0e);
+
+        let event = events.recv_timeout(Duration::from_secs(1)).unwrap();
+        assert!(matches!(event, Some(sdtx::Event::BaseConnection { .. })));
+
+        // The marker reflects the state after reconnecting.
+        let marker = PrettyReconnect::new(snapshot, None, &Models::builtin());
+        let value = serde_json::to_value(&marker).unwrap();
+
+        assert_eq!(value["type"], "reconnected");
+        assert_eq!(value["state"], "detached");
+        assert_eq!(value["mode"], "tablet");
+        assert_eq!(value["status"], "closed");
+    }
+
+    #[test]
+    fn backoff_limit() {
+        let mock = Mock::new();
+        mock.unplug(Duration::from_secs(60));
+
+        let state = State { rx: None, delay: RECONNECT_DELAY_MAX, retry: Instant::now() };
+        let events = EventReceiver { device: &mock, reconnect: true, state: RefCell::new(state) };
+
+        let mut state = events.state.borrow_mut();
+        assert!(events.reopen(&mut state).is_none());
+
+        assert_eq!(state.delay, RECONNECT_DELAY_MAX);
+        assert!(state.retry > Instant::now() + RECONNECT_DELAY_MAX - Duration::from_secs(1));
+    }
+}
@@ -0,0 +1,309 @@
+use std::collections::HashSet;
+use std::time::Duration;
+
+use crate::cli::dtx::inhibit::Inhibitor;
+use crate::sys;
+use crate::sys::logind::{Logind, Signal};
+
+use anyhow::{Context, Result};
+
+
+const POLL_INTERVAL: Duration = Duration::from_millis(250);
+
+const INHIBITOR_WHO: &str = "surface-control";
+const INHIBITOR_WHY: &str = "Lock the clipboard latch before suspending";
+
+
+pub fn command() -> clap::Command {
+    use clap::Arg;
+
+    clap::Command::new("guard")
+        .about("Lock the latch while the system is suspended")
+        .arg(Arg::new("session-lock")
+            .help("Also lock the latch while any session is locked")
+            .long("session-lock")
+            .action(clap::ArgAction::SetTrue))
+}
+
+pub fn execute(m: &clap::ArgMatches) -> Result<()> {
+    sys::signal::catch_termination()
+        .context("Failed to set up signal handler")?;
+
+    let logind = Logind::connect()
+        .context("Failed to connect to logind")?;
+
+    let device = sys::dtx::open()
+        .context("Failed to open DTX device")?;
+
+    let mut guard = Guard::new(&*device, &logind, m.get_flag("quiet"), m.get_flag("session-lock"))?;
+
+    while sys::signal::received().is_none() {
+        guard.poll(POLL_INTERVAL)?;
+    }
+
+    guard.release("exiting");
+    Ok(())
+}
+
+
+/// Keeps the latch locked while the system is suspended or a session is
+/// locked.
+///
+/// Locking goes through the same inhibitor registry as 'inhibit'. Releasing
+/// restores the state from before locking, i.e. the latch is only unlocked
+/// if it has not been locked otherwise.
+struct Guard<'a> {
+    device: &'a dyn sys::dtx::Backend,
+    logind: &'a Logind,
+    quiet: bool,
+    sleeping: bool,
+    locked_sessions: HashSet<String>,
+    inhibitor: Option<Inhibitor<'a>>,
+    delay: Option<sys::logind::InhibitorLock>,
+}
+
+impl<'a> Guard<'a> {
+    fn new(device: &'a dyn sys::dtx::Backend, logind: &'a Logind, quiet: bool, session_lock: bool) -> Result<Self> {
+        let mut guard = Guard {
+            device,
+            logind,
+            quiet,
+            sleeping: false,
+            locked_sessions: HashSet::new(),
+            inhibitor: None,
+            delay: None,
+        };
+
+        if session_lock {
+            logind.watch_sessions()
+                .context("Failed to subscribe to session signals")?;
+
+            // Sessions locked before we started won't send a signal.
+            let locked = logind.locked_sessions()
+                .context("Failed to get locked sessions")?;
+
+            guard.locked_sessions.extend(locked);
+            guard.update("session already locked");
+        }
+
+        guard.inhibit()?;
+        Ok(guard)
+    }
+
+    /// Wait up to `timeout` for logind signals and handle them.
+    fn poll(&mut self, timeout: Duration) -> Result<()> {
+        let signals = self.logind.poll(timeout)
+            .context("Failed to receive logind signals")?;
+
+        for signal in signals {
+            match signal {
+                Signal::PrepareForSleep(true) => {
+                    self.sleeping = true;
+                    self.update("suspending");
+
+                    // We are done, let the system go to sleep.
+                    self.delay = None;
+                },
+                Signal::PrepareForSleep(false) => {
+                    self.sleeping = false;
+                    self.update("resumed");
+                    self.inhibit()?;
+                },
+                Signal::Lock { session } => {
+                    self.locked_sessions.insert(session);
+                    self.update("session locked");
+                },
+                Signal::Unlock { session } => {
+                    self.locked_sessions.remove(&session);
+                    self.update("session unlocked");
+                },
+            }
+        }
+
+        Ok(())
+    }
+
+    fn inhibit(&mut self) -> Result<()> {
+        if self.delay.is_none() {
+            let lock = self.logind.inhibit_sleep(INHIBITOR_WHO, INHIBITOR_WHY)
+                .context("Failed to take sleep inhibitor lock")?;
+
+            self.delay = Some(lock);
+        }
+
+        Ok(())
+    }
+
+    fn update(&mut self, reason: &str) {
+        let lock = self.sleeping || !self.locked_sessions.is_empty();
+
+        match (lock, self.inhibitor.is_some()) {
+            (true, false) => match Inhibitor::acquire(self.device) {
+                Ok(inhibitor) => {
+                    self.inhibitor = Some(inhibitor);
+                    self.log(&format!("Latch locked ({reason})"));
+                },
+                // Don't bail out here: we still need to let the system sleep.
+                Err(e) => eprintln!("Error: {e:#}"),
+            },
+            (false, true) => self.release(reason),
+            _ => (),
+        }
+    }
+
+    fn release(&mut self, reason: &str) {
+        if let Some(inhibitor) = self.inhibitor.take() {
+            match inhibitor.release() {
+                Ok(()) => self.log(&format!("Latch lock released ({reason})")),
+                Err(e) => eprintln!("Error: {e:#}"),
+            }
+        }
+    }
+
+    fn log(&self, msg: &str) {
+        if !self.quiet {
+            println!("{msg}");
+        }
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cli::dtx::inhibit;
+    use crate::sys::dtx::Mock;
+    use crate::sys::testbus::TestBus;
+
+    use std::os::unix::io::IntoRawFd;
+    use std::sync::mpsc;
+
+    use dbus::arg::{OwnedFd, Variant};
+    use dbus::blocking::Connection;
+    use dbus::channel::{MatchingReceiver, Sender};
+    use dbus::message::{Message, MatchRule};
+
+    const LOGIND: &str = "org.freedesktop.login1";
+    const MANAGER_PATH: &str = "/org/freedesktop/login1";
+    const MANAGER_IFACE: &str = "org.freedesktop.login1.Manager";
+    const SESSION_IFACE: &str = "org.freedesktop.login1.Session";
+
+    /// Session that is locked before the guard starts.
+    const SESSION_LOCKED: &str = "/org/freedesktop/login1/session/_31";
+
+    /// Session that is unlocked initially.
+    const SESSION_UNLOCKED: &str = "/org/freedesktop/login1/session/_32";
+
+    /// Run a stub logind with two sessions, reporting calls to `Inhibit`.
+    fn serve(bus: &TestBus) -> mpsc::Receiver<String> {
+        let conn = bus.connect();
+        conn.request_name(LOGIND, false, true, false).unwrap();
+
+        let (tx, inhibits) = mpsc::channel();
+
+        conn.start_receive(MatchRule::new_method_call(), Box::new(move |msg: Message, conn: &Connection| {
+            let reply = match msg.member().as_deref() {
+                Some("Inhibit") => {
+                    let (what, _, _, mode): (String, String, String, String) = msg.read4().unwrap();
+                    tx.send(format!("{what}:{mode}")).unwrap();
+
+                    let file = std::fs::File::open("/dev/null").unwrap();
+                    let fd = unsafe { OwnedFd::new(file.into_raw_fd()) };
+
+                    msg.method_return().append1(fd)
+                },
+                Some("ListSessions") => {
+                    let sessions = vec![
+                        ("1", 1000u32, "user", "seat0", dbus::Path::from(SESSION_LOCKED)),
+                        ("2", 1001u32, "other", "seat0", dbus::Path::from(SESSION_UNLOCKED)),
+                    ];
+
+                    msg.method_return().append1(sessions)
+                },
+                Some("Get") => {
+                    let (iface, name): (String, String) = msg.read2().unwrap();
+                    assert_eq!((iface.as_str(), name.as_str()), (SESSION_IFACE, "LockedHint"));
+
+                    let locked = &*msg.path().unwrap() == SESSION_LOCKED;
+                    msg.method_return().append1(Variant(locked))
+                },
+                _ => return true,
+            };
+
+            conn.send(reply).unwrap();
+            true
+        }));
+
+        // Stops once the daemon is gone.
+        std::thread::spawn(move || while conn.process(Duration::from_millis(50)).is_ok() {});
+
+        inhibits
+    }
+
+    fn poll_until(guard: &mut Guard, cond: impl Fn(&Guard) -> bool) {
+        for _ in 0..50 {
+            guard.poll(Duration::from_millis(100)).unwrap();
+            if cond(guard) {
+                return;
+            }
+        }
+
+        panic!("condition not met");
+    }
+
+    #[test]
+    fn guard() {
+        let _files = inhibit::TEST_FILES.lock().unwrap_or_else(|e| e.into_inner());
+
+        let bus = match TestBus::spawn() {
+            Some(bus) => bus,
+            None => return eprintln!("dbus-daemon not available, skipping"),
+        };
+
+        let inhibits = serve(&bus);
+
+        let logind = Logind::with_connection(bus.connect()).unwrap();
+
+        let mock = Mock::new();
+        let server = bus.connect();
+
+        let emit = |path: &str, iface: &str, member: &str, sleep: Option<bool>| {
+            let mut msg = Message::new_signal(path, iface, member).unwrap();
+            if let Some(sleep) = sleep {
+                msg = msg.append1(sleep);
+            }
+            server.send(msg).unwrap();
+        };
+
+        // Already locked session, picked up at startup.
+        let mut guard = Guard::new(&mock, &logind, true, true).unwrap();
+        assert_eq!(inhibits.recv_timeout(Duration::from_secs(5)).unwrap(), "sleep:delay");
+        assert!(mock.is_locked());
+
+        emit(SESSION_LOCKED, SESSION_IFACE, "Unlock", None);
+        poll_until(&mut guard, |_| !mock.is_locked());
+
+        // Suspend releases the delay lock, resume takes a new one.
+        emit(MANAGER_PATH, MANAGER_IFACE, "PrepareForSleep", Some(true));
+        poll_until(&mut guard, |_| mock.is_locked());
+        assert!(guard.delay.is_none());
+
+        emit(MANAGER_PATH, MANAGER_IFACE, "PrepareForSleep", Some(false));
+        poll_until(&mut guard, |_| !mock.is_locked());
+        assert_eq!(inhibits.recv_timeout(Duration::from_secs(5)).unwrap(), "sleep:delay");
+        assert!(guard.delay.is_some());
+
+        // A latch locked via 'lock' stays locked.
+        inhibit::lock(&mock).unwrap();
+
+        emit(SESSION_UNLOCKED, SESSION_IFACE, "Lock", None);
+        poll_until(&mut guard, |g| g.inhibitor.is_some());
+
+        emit(SESSION_UNLOCKED, SESSION_IFACE, "Unlock", None);
+        poll_until(&mut guard, |g| g.inhibitor.is_none());
+        assert!(mock.is_locked());
+
+        inhibit::unlock(&mock).unwrap();
+        assert!(!mock.is_locked());
+    }
+}
@@ -0,0 +1,344 @@
+use std::convert::TryFrom;
+use std::fs::{File, OpenOptions};
+use std::path::Path;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use crate::cli::dtx::cancel_reason_str;
+use crate::sys;
+
+use anyhow::{Context, Result};
+use nix::fcntl::{Flock, FlockArg};
+
+
+/// State of the tracker run by `state --follow`, read by `state`.
+#[cfg(not(test))]
+pub const STATE_FILE: &str = "/run/surface-control/dtx-state";
+
+/// Keep tests from interfering with a running tracker.
+#[cfg(test)]
+pub const STATE_FILE: &str = "/tmp/surface-control-test/dtx-state";
+
+
+/// Phase of the detachment handshake with the EC.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Phase {
+    /// No detachment in progress.
+    Idle,
+
+    /// Detachment requested, waiting for confirmation.
+    Requested,
+
+    /// Detachment confirmed, waiting for the latch to open.
+    Confirmed,
+
+    /// Latch opened, the clipboard can be detached.
+    Opened,
+
+    /// The last detachment has been canceled, with the given reason.
+    Canceled(String),
+}
+
+impl Phase {
+    pub fn name(&self) -> &'static str {
+        match self {
+            Phase::Idle        => "idle",
+            Phase::Requested   => "requested",
+            Phase::Confirmed   => "confirmed",
+            Phase::Opened      => "opened",
+            Phase::Canceled(_) => "canceled",
+        }
+    }
+
+    pub fn reason(&self) -> Option<&str> {
+        match self {
+            Phase::Canceled(reason) => Some(reason),
+            _                       => None,
+        }
+    }
+
+    /// Best guess of the phase from the latch status alone.
+    fn from_latch(status: sdtx::LatchStatus) -> Self {
+        match status {
+            sdtx::LatchStatus::Opened => Phase::Opened,
+            _                         => Phase::Idle,
+        }
+    }
+}
+
+impl std::fmt::Display for Phase {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self.reason() {
+            Some(reason) => write!(f, "{}: {reason}", self.name()),
+            None         => write!(f, "{}", self.name()),
+        }
+    }
+}
+
+
+/// Tracks the handshake phase from the DTX events seen.
+///
+/// The EC does not report confirmations, so whoever confirms has to tell the
+/// tracker via [`Tracker::confirm`].
+#[derive(Debug, Clone)]
+pub struct Tracker {
+    phase: Phase,
+    since: SystemTime,
+}
+
+impl Tracker {
+    /// Start tracking from the current latch status of the device.
+    pub fn from_device(device: &dyn sys::dtx::Backend) -> Result<Self> {
+        let status = device.get_latch_status()
+            .context("Failed to get latch status")?;
+
+        Ok(Tracker { phase: Phase::from_latch(status), since: SystemTime::now() })
+    }
+
+    pub fn phase(&self) -> &Phase {
+        &self.phase
+    }
+
+    pub fn since(&self) -> SystemTime {
+        self.since
+    }
+
+    /// Time spent in the current phase so far.
+    pub fn elapsed(&self) -> Duration {
+        self.since.elapsed().unwrap_or_default()
+    }
+
+    /// Update the phase based on the given event. Returns `true` if the phase
+    /// has changed.
+    pub fn update(&mut self, event: &sdtx::Event) -> bool {
+        use sdtx::Event;
+        use sdtx::event::{CancelReason, LatchStatus};
+
+        let phase = match event {
+            Event::Request => Phase::Requested,
+            Event::Cancel { reason } => Phase::Canceled(cancel_reason_str(reason)),
+            Event::LatchStatus { status: LatchStatus::Opened } => Phase::Opened,
+            Event::LatchStatus { status: LatchStatus::Closed } => Phase::Idle,
+            Event::LatchStatus { status: LatchStatus::Error(err) } => {
+                Phase::Canceled(cancel_reason_str(&CancelReason::Hardware(*err)))
+            },
+            _ => return false,
+        };
+
+        self.set(phase)
+    }
+
+    /// Reset the phase from the latch status after events may have been
+    /// missed, e.g. when reconnecting. Returns `true` if the phase has changed.
+    pub fn resync(&mut self, status: sdtx::LatchStatus) -> bool {
+        self.set(Phase::from_latch(status))
+    }
+
+    /// Record that the pending request has been confirmed.
+    pub fn confirm(&mut self) -> bool {
+        if self.phase != Phase::Requested {
+            return false;
+        }
+
+        self.set(Phase::Confirmed)
+    }
+
+    fn set(&mut self, phase: Phase) -> bool {
+        if phase == self.phase {
+            return false;
+        }
+
+        self.phase = phase;
+        self.since = SystemTime::now();
+        true
+    }
+
+    /// Write the current phase to the given state file, on behalf of the
+    /// current process.
+    pub fn store(&self, path: &Path) -> Result<()> {
+        let _lock = lock(path)?;
+        self.write(path, std::process::id())
+    }
+
+    /// Read the state file written by a running tracker. Returns `None` if
+    /// there is no such file or the process that wrote it has exited.
+    pub fn load(path: &Path) -> Result<Option<Self>> {
+        Ok(Tracker::read(path)?.map(|(_, tracker)| tracker))
+    }
+
+    /// Mark a pending request as confirmed in the state file of a running
+    /// tracker, as it cannot see confirmations itself.
+    pub fn store_confirmed(path: &Path) -> Result<()> {
+        // Keep the tracker from storing a new phase in between.
+        let _lock = lock(path)?;
+
+        if let Some((pid, mut tracker)) = Tracker::read(path)? {
+            if tracker.confirm() {
+                tracker.write(path, pid)?;
+            }
+        }
+
+        Ok(())
+    }
+
+    fn write(&self, path: &Path, pid: u32) -> Result<()> {
+        let since = self.since.duration_since(UNIX_EPOCH).unwrap_or_default();
+
+        let text = serde_json::json!({
+            "pid": pid,
+            "phase": self.phase.name(),
+            "reason": self.phase.reason(),
+            "since": since.as_secs_f64(),
+        });
+
+        if let Some(dir) = path.parent() {
+            std::fs::create_dir_all(dir)
+                .with_context(|| format!("Failed to create directory {dir:?}"))?;
+        }
+
+        // Write to a temporary file first so readers never see partial data.
+        let tmp = path.with_extension(format!("tmp.{}", std::process::id()));
+        std::fs::write(&tmp, format!("{text}\n"))
+            .and_then(|_| std::fs::rename(&tmp, path))
+            .with_context(|| format!("Failed to write state file {path:?}"))
+    }
+
+    fn read(path: &Path) -> Result<Option<(u32, Self)>> {
+        let text = match std::fs::read_to_string(path) {
+            Ok(text) => text,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(None),
+            Err(e) => return Err(e).with_context(|| format!("Failed to read state file {path:?}")),
+        };
+
+        let value: serde_json::Value = serde_json::from_str(&text)
+            .with_context(|| format!("Invalid state file {path:?}"))?;
+
+        let pid = value.get("pid")
+            .and_then(|v| v.as_u64())
+            .and_then(|pid| u32::try_from(pid).ok())
+            .unwrap_or_default();
+
+        if pid == 0 || !sys::pidfile::is_alive(pid) {
+            return Ok(None);
+        }
+
+        let reason = value.get("reason").and_then(|v| v.as_str()).unwrap_or_default();
+
+        let phase = match value.get("phase").and_then(|v| v.as_str()) {
+            Some("idle")      => Phase::Idle,
+            Some("requested") => Phase::Requested,
+            Some("confirmed") => Phase::Confirmed,
+            Some("opened")    => Phase::Opened,
+            Some("canceled")  => Phase::Canceled(reason.to_owned()),
+            _ => anyhow::bail!("Invalid state file {path:?}: unknown phase"),
+        };
+
+        let since = value.get("since")
+            .and_then(|v| v.as_f64())
+            .and_then(|t| Duration::try_from_secs_f64(t).ok())
+            .with_context(|| format!("Invalid state file {path:?}: missing timestamp"))?;
+
+        Ok(Some((pid, Tracker { phase, since: UNIX_EPOCH + since })))
+    }
+}
+
+
+/// Confirm a pending detachment and record it in the state file of a running
+/// tracker. All confirmations should go through here.
+pub fn confirm(device: &dyn sys::dtx::Backend) -> Result<()> {
+    device.latch_confirm()
+        .context("Failed to send confirmation")?;
+
+    // The confirmation has been sent at this point, so don't fail on this.
+    if let Err(e) = Tracker::store_confirmed(Path::new(STATE_FILE)) {
+        eprintln!("Warning: Failed to record confirmation: {e:#}");
+    }
+
+    Ok(())
+}
+
+/// Take an exclusive lock on the state file. The state file itself is
+/// replaced on every write, so this uses a separate lock file next to it.
+fn lock(path: &Path) -> Result<Flock<File>> {
+    let path = path.with_extension("lock");
+
+    if let Some(dir) = path.parent() {
+        std::fs::create_dir_all(dir)
+            .with_context(|| format!("Failed to create directory {dir:?}"))?;
+    }
+
+    let file = OpenOptions::new()
+        .write(true)
+        .create(true)
+        .truncate(false)
+        .open(&path)
+        .with_context(|| format!("Failed to open lock file {path:?}"))?;
+
+    Flock::lock(file, FlockArg::LockExclusive)
+        .map_err(|(_, errno)| errno)
+        .with_context(|| format!("Failed to lock {path:?}"))
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::sys::dtx::{Backend, Mock};
+
+    fn temp_dir(name: &str) -> std::path::PathBuf {
+        let dir = std::env::temp_dir().join(format!("surface-test-{name}-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    #[test]
+    fn store_confirmed() {
+        let dir = temp_dir("handshake");
+        let path = dir.join("state");
+
+        // Nothing to confirm without a tracker.
+        Tracker::store_confirmed(&path).unwrap();
+        assert!(Tracker::load(&path).unwrap().is_none());
+
+        let mut tracker = Tracker { phase: Phase::Idle, since: SystemTime::now() };
+        tracker.store(&path).unwrap();
+
+        // Nothing to confirm without a request.
+        Tracker::store_confirmed(&path).unwrap();
+        assert_eq!(Tracker::load(&path).unwrap().unwrap().phase(), &Phase::Idle);
+
+        tracker.update(&sdtx::Event::Request);
+        tracker.store(&path).unwrap();
+
+        Tracker::store_confirmed(&path).unwrap();
+        assert_eq!(Tracker::load(&path).unwrap().unwrap().phase(), &Phase::Confirmed);
+
+        // No temporary files are left behind.
+        let mut files: Vec<_> = std::fs::read_dir(&dir).unwrap()
+            .map(|entry| entry.unwrap().file_name())
+            .collect();
+        files.sort();
+
+        std::fs::remove_dir_all(&dir).unwrap();
+
+        assert_eq!(files, ["state", "state.lock"]);
+    }
+
+    #[test]
+    fn confirm_records_state() {
+        let path = Path::new(STATE_FILE);
+
+        let mock = Mock::new();
+        mock.press();
+
+        let mut tracker = Tracker::from_device(&mock).unwrap();
+        tracker.update(&sdtx::Event::Request);
+        tracker.store(path).unwrap();
+
+        confirm(&mock).unwrap();
+
+        assert_eq!(mock.get_latch_status().unwrap(), sdtx::LatchStatus::Opened);
+        assert_eq!(Tracker::load(path).unwrap().unwrap().phase(), &Phase::Confirmed);
+
+        std::fs::remove_file(path).unwrap();
+    }
+}
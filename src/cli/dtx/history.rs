@@ -0,0 +1,185 @@
+use std::fs::File;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::time::SystemTime;
+
+use nix::fcntl::{Flock, FlockArg};
+
+use crate::cli::dtx::PrettyEvent;
+use crate::cli::dtx::timestamp::Timestamp;
+
+use anyhow::{Context, Result};
+
+
+/// Persistent log of all DTX events seen by the long-running modes.
+#[cfg(not(test))]
+pub const LOG_FILE: &str = "/var/lib/surface-control/dtx-events";
+
+/// Keep tests from writing to the system log.
+#[cfg(test)]
+pub const LOG_FILE: &str = "/tmp/surface-control-test/dtx-events";
+
+
+/// Size above which the log is rotated. Only one rotated log is kept.
+const MAX_LOG_SIZE: u64 = 1 << 20;
+
+
+/// Writer for the persistent event log.
+///
+/// The log uses the recording format (see `monitor --record`), but with
+/// wall-clock times in RFC 3339 format as printed by `monitor --timestamps
+/// rfc3339`. It can thus be replayed like a recording. To avoid duplicate entries, only the process
+/// holding an exclusive lock on the log writes to it. Everyone else retries
+/// on each event, so another process takes over once the writer exits.
+///
+/// Once the log exceeds [`MAX_LOG_SIZE`], it is moved to the path given by
+/// [`rotated_path`] and a new one is started.
+pub struct EventLog {
+    path: PathBuf,
+    file: Option<Flock<File>>,
+    max_size: u64,
+}
+
+impl EventLog {
+    pub fn new<P: Into<PathBuf>>(path: P) -> Self {
+        EventLog { path: path.into(), file: None, max_size: MAX_LOG_SIZE }
+    }
+
+    pub fn write(&mut self, event: sdtx::Event) -> Result<sdtx::Event> {
+        if self.file.is_none() {
+            self.file = self.try_lock();
+        }
+
+        let full = match &self.file {
+            Some(file) => file.metadata().map(|m| m.len() >= self.max_size).unwrap_or(false),
+            None       => return Ok(event),
+        };
+
+        if full {
+            self.rotate()?;
+        }
+
+        let file = match &mut self.file {
+            Some(file) => file,
+            None       => return Ok(event),
+        };
+
+        let event = PrettyEvent { event, time: Some(Timestamp::Realtime(SystemTime::now())), model: None };
+
+        let mut line = serde_json::to_string(&event)
+            .context("Failed to serialize data")?;
+        line.push('\n');
+
+        file.write_all(line.as_bytes())
+            .with_context(|| format!("Failed to write event log {:?}", self.path))?;
+
+        Ok(event.event)
+    }
+
+    /// Move the log we are holding out of the way and start a new one.
+    fn rotate(&mut self) -> Result<()> {
+        let rotated = rotated_path(&self.path);
+
+        std::fs::rename(&self.path, &rotated)
+            .with_context(|| format!("Failed to rotate event log {:?}", self.path))?;
+
+        // Someone else may have picked up the new log in between, in which
+        // case they are the writer now.
+        self.file = self.try_lock();
+        Ok(())
+    }
+
+    /// Open and lock the log. Returns `None` if someone else is already
+    /// writing it or we are not allowed to.
+    fn try_lock(&self) -> Option<Flock<File>> {
+        if let Some(dir) = self.path.parent() {
+            std::fs::create_dir_all(dir).ok()?;
+        }
+
+        let file = std::fs::OpenOptions::new()
+            .append(true)
+            .create(true)
+            .open(&self.path)
+            .ok()?;
+
+        Flock::lock(file, FlockArg::LockExclusiveNonblock).ok()
+    }
+}
+
+
+/// Path of the previous log after rotating.
+pub fn rotated_path(path: &Path) -> PathBuf {
+    let mut name = path.file_name().unwrap_or_default().to_owned();
+    name.push(".1");
+
+    path.with_file_name(name)
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cli::dtx::{matches, record, replay};
+
+    use std::time::Duration;
+
+    #[test]
+    fn wall_clock_time() {
+        let path = std::env::temp_dir().join(format!("surface-test-log-{}", std::process::id()));
+
+        let mut log = EventLog::new(&path);
+        log.write(sdtx::Event::Request).unwrap();
+        drop(log);
+
+        let events = record::load_log(&path).unwrap();
+        std::fs::remove_file(&path).unwrap();
+
+        let (time, event) = &events[0];
+        let age = SystemTime::now().duration_since(*time).unwrap();
+
+        assert!(age < Duration::from_secs(60), "{:?}", age);
+        assert_eq!(event, &sdtx::Event::Request);
+    }
+
+    #[test]
+    fn replay() {
+        let path = std::env::temp_dir().join(format!("surface-test-log-replay-{}", std::process::id()));
+
+        let mut log = EventLog::new(&path);
+        log.write(sdtx::Event::Request).unwrap();
+        std::thread::sleep(Duration::from_millis(100));
+        log.write(sdtx::Event::LatchStatus { status: sdtx::event::LatchStatus::Opened }).unwrap();
+        drop(log);
+
+        // Times are taken relative to the first event, not the epoch.
+        let start = std::time::Instant::now();
+        replay::execute(&matches(&["replay", "-q", path.to_str().unwrap()])).unwrap();
+        std::fs::remove_file(&path).unwrap();
+
+        assert!(start.elapsed() >= Duration::from_millis(100));
+        assert!(start.elapsed() < Duration::from_secs(5), "{:?}", start.elapsed());
+    }
+
+    #[test]
+    fn rotate() {
+        let dir = std::env::temp_dir().join(format!("surface-test-rotate-{}", std::process::id()));
+        let path = dir.join("log");
+
+        let mut log = EventLog::new(&path);
+        log.max_size = 150;
+
+        for _ in 0..10 {
+            log.write(sdtx::Event::Request).unwrap();
+        }
+        drop(log);
+
+        let current = record::load(&path).unwrap();
+        let rotated = record::load(&rotated_path(&path)).unwrap();
+        std::fs::remove_dir_all(&dir).unwrap();
+
+        // Each entry is about 60 bytes, so the log is rotated every third
+        // one. Only the last rotation is kept.
+        assert_eq!(rotated.len(), 3);
+        assert_eq!(current.len(), 1);
+    }
+}
@@ -0,0 +1,243 @@
+use std::io::Write;
+use std::time::{Duration, Instant};
+
+use crate::cli::dtx::{send_heartbeat, HEARTBEAT_INTERVAL};
+use crate::cli::dtx::events::{self, EventReceiver, Received};
+use crate::cli::dtx::handshake::{self, Phase, Tracker};
+use crate::sys;
+
+use anyhow::{Context, Result};
+
+
+const POLL_INTERVAL: Duration = Duration::from_millis(100);
+
+
+pub fn command() -> clap::Command {
+    use clap::Arg;
+
+    clap::Command::new("hold")
+        .about("Keep a detachment in progress alive by sending heartbeats")
+        .arg(Arg::new("max")
+            .help("Maximum time to keep the detachment alive, in seconds or with unit (e.g. 90s, 2m)")
+            .long("max")
+            .value_name("DURATION")
+            .value_parser(parse_duration)
+            .default_value("60s"))
+        .arg(Arg::new("then")
+            .help("What to do once the time is up or on Ctrl-C")
+            .long("then")
+            .value_name("ACTION")
+            .value_parser(["confirm", "cancel", "nothing"])
+            .default_value("nothing"))
+        .arg(events::reconnect_arg())
+}
+
+pub fn execute(m: &clap::ArgMatches) -> Result<()> {
+    let device = sys::dtx::open()
+        .context("Failed to open DTX device")?;
+
+    run(&*device, m)
+}
+
+pub fn run(device: &dyn sys::dtx::Backend, m: &clap::ArgMatches) -> Result<()> {
+    let quiet = m.get_flag("quiet");
+    let reconnect = m.get_flag("reconnect");
+    let max: Duration = *m.get_one("max").unwrap();
+    let then: &String = m.get_one("then").unwrap();
+
+    sys::signal::catch_termination()
+        .context("Failed to set up signal handler")?;
+
+    let events = EventReceiver::open(device)?
+        .reconnecting(reconnect);
+
+    let mut tracker = Tracker::from_device(device)?;
+
+    let start = Instant::now();
+    let deadline = start + max;
+
+    let mut next_heartbeat = start;
+    while Instant::now() < deadline && sys::signal::received().is_none() {
+        let now = Instant::now();
+
+        if now >= next_heartbeat {
+            send_heartbeat(device, reconnect)?;
+
+            next_heartbeat = now + HEARTBEAT_INTERVAL;
+
+            if !quiet {
+                let remaining = deadline.saturating_duration_since(now);
+                print!("\rHolding detachment: {:3.0}s remaining", remaining.as_secs_f64().ceil());
+                let _ = std::io::stdout().flush();
+            }
+        }
+
+        match events.recv_any_timeout(POLL_INTERVAL)? {
+            Some(Received::Event(event))          => { tracker.update(&event); },
+            Some(Received::Reconnected(snapshot)) => { tracker.resync(snapshot.latch); },
+            None => (),
+        }
+
+        match tracker.phase() {
+            Phase::Canceled(reason) => {
+                if !quiet {
+                    println!();
+                }
+                anyhow::bail!("Detachment canceled: {reason}");
+            },
+            Phase::Opened => {
+                // No need for heartbeats anymore, and nothing left to do.
+                if !quiet {
+                    println!();
+                    println!("Latch opened, clipboard can be detached");
+                }
+                return Ok(());
+            },
+            _ => (),
+        }
+    }
+
+    if !quiet {
+        println!();
+    }
+
+    match then.as_str() {
+        "confirm" => {
+            handshake::confirm(device)?;
+
+            if !quiet {
+                println!("Clipboard detachment confirmed");
+            }
+        },
+        "cancel" => {
+            device.latch_cancel()
+                .context("Failed to cancel detachment")?;
+
+            if !quiet {
+                println!("Clipboard detachment canceled");
+            }
+        },
+        _ => (),
+    }
+
+    Ok(())
+}
+
+
+/// Parse a duration given in seconds, optionally with a unit suffix of `ms`,
+/// `s`, `m`, or `h`.
+fn parse_duration(s: &str) -> std::result::Result<Duration, String> {
+    let split = s.find(|c: char| !c.is_ascii_digit()).unwrap_or(s.len());
+    let (value, unit) = s.split_at(split);
+
+    let value: u64 = value.parse()
+        .map_err(|_| format!("invalid duration '{s}'"))?;
+
+    let duration = match unit {
+        "ms"     => Duration::from_millis(value),
+        "" | "s" => Duration::from_secs(value),
+        "m"      => Duration::from_secs(value.saturating_mul(60)),
+        "h"      => Duration::from_secs(value.saturating_mul(3600)),
+        _        => return Err(format!("invalid unit '{unit}', expected one of ms, s, m, h")),
+    };
+
+    Ok(duration)
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cli::dtx::matches;
+    use crate::sys::dtx::{Backend, Mock};
+
+    #[test]
+    fn keeps_request_alive() {
+        let mock = Mock::new();
+        mock.set_request_timeout(Duration::from_millis(1500));
+        mock.press();
+
+        run(&mock, &matches(&["hold", "-q", "--max", "3", "--then", "confirm"])).unwrap();
+
+        assert_eq!(mock.get_latch_status().unwrap(), sdtx::LatchStatus::Opened);
+    }
+
+    #[test]
+    fn canceled() {
+        let mock = Mock::new();
+        mock.press();
+
+        let user = mock.clone();
+        std::thread::spawn(move || {
+            std::thread::sleep(Duration::from_millis(200));
+            user.detach();
+            user.press();
+        });
+
+        let err = run(&mock, &matches(&["hold", "-q", "--max", "2"])).unwrap_err();
+
+        assert!(err.to_string().contains("Detachment canceled"), "{:#}", err);
+    }
+
+    #[test]
+    fn reconnect() {
+        let mock = Mock::new();
+        mock.press();
+
+        let user = mock.clone();
+        std::thread::spawn(move || {
+            std::thread::sleep(Duration::from_millis(200));
+            user.unplug(Duration::from_millis(1500));
+        });
+
+        run(&mock, &matches(&["hold", "-q", "--max", "3", "--then", "confirm", "--reconnect"])).unwrap();
+
+        assert_eq!(mock.get_latch_status().unwrap(), sdtx::LatchStatus::Opened);
+    }
+
+    #[test]
+    fn error_without_reconnect() {
+        let mock = Mock::new();
+        mock.press();
+
+        let user = mock.clone();
+        std::thread::spawn(move || {
+            std::thread::sleep(Duration::from_millis(200));
+            user.unplug(Duration::from_millis(500));
+        });
+
+        assert!(run(&mock, &matches(&["hold", "-q", "--max", "3"])).is_err());
+    }
+
+    #[test]
+    fn stops_when_opened() {
+        let mock = Mock::new();
+        mock.press();
+
+        let user = mock.clone();
+        std::thread::spawn(move || {
+            std::thread::sleep(Duration::from_millis(200));
+            user.latch_confirm().unwrap();
+        });
+
+        let start = Instant::now();
+        run(&mock, &matches(&["hold", "-q", "--max", "10s", "--then", "cancel"])).unwrap();
+
+        assert!(start.elapsed() < Duration::from_secs(5), "{:?}", start.elapsed());
+        assert_eq!(mock.get_latch_status().unwrap(), sdtx::LatchStatus::Opened);
+    }
+
+    #[test]
+    fn durations() {
+        assert_eq!(parse_duration("60"), Ok(Duration::from_secs(60)));
+        assert_eq!(parse_duration("60s"), Ok(Duration::from_secs(60)));
+        assert_eq!(parse_duration("500ms"), Ok(Duration::from_millis(500)));
+        assert_eq!(parse_duration("2m"), Ok(Duration::from_secs(120)));
+        assert_eq!(parse_duration("1h"), Ok(Duration::from_secs(3600)));
+
+        assert!(parse_duration("").is_err());
+        assert!(parse_duration("s").is_err());
+        assert!(parse_duration("1.5s").is_err());
+        assert!(parse_duration("10d").is_err());
+    }
+}
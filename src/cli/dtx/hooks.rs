@@ -0,0 +1,368 @@
+use std::path::{Path, PathBuf};
+use std::process::ExitStatus;
+use std::sync::mpsc;
+use std::thread::JoinHandle;
+use std::time::{Duration, Instant};
+
+use crate::cli::dtx::{event_type_str, load_models, PrettyEvent, PrettyReconnect};
+use crate::cli::dtx::events::{self, EventReceiver, Received, Snapshot};
+use crate::cli::dtx::history::{EventLog, LOG_FILE};
+use crate::sys;
+use crate::sys::bases::Models;
+
+use anyhow::{Context, Result};
+
+
+const DEFAULT_HOOK_DIR: &str = "/etc/surface-control/dtx-hooks";
+const HOOK_POLL_INTERVAL: Duration = Duration::from_millis(50);
+
+/// Environment variables for the fields of the serialized event.
+const EVENT_VARS: [(&str, &str); 9] = [
+    ("type",        "SDTX_EVENT"),
+    ("reason",      "SDTX_CANCEL_REASON"),
+    ("state",       "SDTX_BASE_STATE"),
+    ("device-type", "SDTX_BASE_TYPE"),
+    ("id",          "SDTX_BASE_ID"),
+    ("model",       "SDTX_BASE_MODEL"),
+    ("status",      "SDTX_LATCH_STATUS"),
+    ("mode",        "SDTX_DEVICE_MODE"),
+    ("data",        "SDTX_DATA"),
+];
+
+
+pub fn command() -> clap::Command {
+    use clap::Arg;
+
+    clap::Command::new("hooks")
+        .about("Run hook scripts on DTX events")
+        .arg(Arg::new("dir")
+            .help("Hook directory, containing '<event-type>.d' and 'any.d' subdirectories")
+            .long("dir")
+            .value_name("DIR")
+            .value_parser(clap::value_parser!(PathBuf))
+            .default_value(DEFAULT_HOOK_DIR))
+        .arg(Arg::new("timeout")
+            .help("Maximum time in seconds a single hook may run before it is killed")
+            .long("timeout")
+            .value_name("SECS")
+            .value_parser(clap::value_parser!(u64))
+            .default_value("30"))
+        .arg(events::reconnect_arg())
+}
+
+pub fn execute(m: &clap::ArgMatches) -> Result<()> {
+    let device = sys::dtx::open()
+        .context("Failed to open DTX device")?;
+
+    run(&*device, m)
+}
+
+pub fn run(device: &dyn sys::dtx::Backend, m: &clap::ArgMatches) -> Result<()> {
+    let quiet = m.get_flag("quiet");
+    let dir: &PathBuf = m.get_one("dir").unwrap();
+    let timeout = Duration::from_secs(*m.get_one("timeout").unwrap());
+
+    let models = load_models();
+
+    let events = EventReceiver::open(device)?
+        .reconnecting(m.get_flag("reconnect"));
+
+    let mut log = EventLog::new(LOG_FILE);
+    let mut hooks = Dispatcher::new(dir, timeout, quiet);
+
+    loop {
+        match events.recv_any()? {
+            Received::Event(event) => {
+                let event = log.write(event)?;
+                hooks.dispatch(&models, event)?;
+            },
+            Received::Reconnected(snapshot) => {
+                hooks.dispatch_reconnect(&models, snapshot)?;
+            },
+        }
+    }
+}
+
+
+/// Runs the hooks matching each event.
+///
+/// Hooks run on a single worker thread in the background so that slow hooks
+/// don't hold up the event loop. They still run one after another in the
+/// order of their events, and sorted by name for a single event. Dropping
+/// the dispatcher waits for all queued hooks to finish.
+pub struct Dispatcher {
+    dir: PathBuf,
+    queue: Option<mpsc::Sender<Job>>,
+    worker: Option<JoinHandle<()>>,
+}
+
+/// Hooks to run for a single event, along with their environment.
+struct Job {
+    hooks: Vec<PathBuf>,
+    env: Vec<(&'static str, String)>,
+}
+
+impl Dispatcher {
+    pub fn new<P: Into<PathBuf>>(dir: P, timeout: Duration, quiet: bool) -> Self {
+        let (tx, rx) = mpsc::channel::<Job>();
+
+        let worker = std::thread::spawn(move || {
+            for job in rx {
+                for hook in job.hooks {
+                    let result = run_hook(&hook, &job.env, timeout);
+                    log(quiet, &hook, &job.env, &result);
+                }
+            }
+        });
+
+        Dispatcher { dir: dir.into(), queue: Some(tx), worker: Some(worker) }
+    }
+
+    /// Run the hooks matching the given event.
+    pub fn dispatch(&mut self, models: &Models, event: sdtx::Event) -> Result<()> {
+        let ty = event_type_str(&event);
+        self.submit(ty, &PrettyEvent::new(event, None, models))
+    }
+
+    /// Run the 'reconnected.d' and 'any.d' hooks after reconnecting to the
+    /// device, with the re-read state as environment.
+    fn dispatch_reconnect(&mut self, models: &Models, snapshot: Snapshot) -> Result<()> {
+        self.submit(Some("reconnected"), &PrettyReconnect::new(snapshot, None, models))
+    }
+
+    fn submit<T>(&mut self, ty: Option<&str>, item: &T) -> Result<()>
+    where
+        T: serde::Serialize
+    {
+        let mut hooks = Vec::new();
+        if let Some(ty) = ty {
+            hooks.extend(find_hooks(&self.dir.join(format!("{ty}.d")))?);
+        }
+        hooks.extend(find_hooks(&self.dir.join("any.d"))?);
+
+        if hooks.is_empty() {
+            return Ok(());
+        }
+
+        let job = Job { hooks, env: event_env(item)? };
+
+        // The worker only stops once the queue is closed, unless it panicked.
+        self.queue.as_ref()
+            .and_then(|queue| queue.send(job).ok())
+            .context("Hook worker has stopped unexpectedly")
+    }
+}
+
+impl Drop for Dispatcher {
+    fn drop(&mut self) {
+        // Close the queue so that the worker exits after the remaining jobs.
+        self.queue.take();
+
+        if let Some(worker) = self.worker.take() {
+            let _ = worker.join();
+        }
+    }
+}
+
+
+/// Environment variables describing the event, using the same names and
+/// values as its JSON representation. Base IDs are given in hex, as shown
+/// everywhere else.
+fn event_env<T: serde::Serialize>(event: &T) -> Result<Vec<(&'static str, String)>> {
+    let value = serde_json::to_value(event)
+        .context("Failed to serialize data")?;
+
+    let env = EVENT_VARS.iter()
+        .filter_map(|(field, var)| {
+            let value = match (*field, value.get(field)?) {
+                ("id", serde_json::Value::Number(n)) => format!("{:#04x}", n.as_u64()?),
+                (_, serde_json::Value::String(s))    => s.clone(),
+                (_, value)                           => value.to_string(),
+            };
+
+            Some((*var, value))
+        })
+        .collect();
+
+    Ok(env)
+}
+
+/// Executable files in the given directory, sorted by name.
+fn find_hooks(dir: &Path) -> Result<Vec<PathBuf>> {
+    use std::os::unix::fs::PermissionsExt;
+
+    let entries = match std::fs::read_dir(dir) {
+        Ok(entries) => entries,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(Vec::new()),
+        Err(e) => return Err(e).with_context(|| format!("Failed to read hook directory {dir:?}")),
+    };
+
+    let mut hooks: Vec<PathBuf> = entries.flatten()
+        .filter(|e| {
+            let name = e.file_name().to_string_lossy().into_owned();
+            !name.starts_with('.') && !name.ends_with('~')
+        })
+        .filter(|e| {
+            e.path().metadata()
+                .is_ok_and(|m| m.is_file() && m.permissions().mode() & 0o111 != 0)
+        })
+        .map(|e| e.path())
+        .collect();
+
+    hooks.sort();
+    Ok(hooks)
+}
+
+/// Run a single hook. Returns `None` if it timed out and has been killed.
+fn run_hook(hook: &Path, env: &[(&str, String)], timeout: Duration) -> Result<Option<ExitStatus>> {
+    // Keep our stdout for the log, which may be parsed as JSON.
+    let mut child = std::process::Command::new(hook)
+        .envs(env.iter().map(|(k, v)| (k, v)))
+        .stdout(std::io::stderr())
+        .spawn()
+        .context("Failed to run hook")?;
+
+    let deadline = Instant::now() + timeout;
+
+    loop {
+        if let Some(status) = child.try_wait().context("Failed to wait for hook")? {
+            return Ok(Some(status));
+        }
+
+        if Instant::now() >= deadline {
+            let _ = child.kill();
+            let _ = child.wait();
+            return Ok(None);
+        }
+
+        std::thread::sleep(HOOK_POLL_INTERVAL);
+    }
+}
+
+fn log(quiet: bool, hook: &Path, env: &[(&str, String)], result: &Result<Option<ExitStatus>>) {
+    let event = env.iter()
+        .find(|(k, _)| *k == "SDTX_EVENT")
+        .map(|(_, v)| v.as_str())
+        .unwrap_or_default();
+
+    if !quiet {
+        match result {
+            Ok(Some(status)) => println!("Hook {hook:?} ({event}): {status}"),
+            Ok(None)         => println!("Hook {hook:?} ({event}): timed out"),
+            Err(e)           => println!("Hook {hook:?} ({event}): {e:#}"),
+        }
+
+    } else {
+        let (code, error) = match result {
+            Ok(Some(status)) => (status.code(), None),
+            Ok(None)         => (None, Some("timed out".to_owned())),
+            Err(e)           => (None, Some(format!("{e:#}"))),
+        };
+
+        let text = serde_json::json!({
+            "hook": hook,
+            "event": event,
+            "exit-code": code,
+            "error": error,
+        });
+
+        println!("{text}");
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::sys::dtx::{Backend, Mock};
+
+    use std::os::unix::fs::PermissionsExt;
+
+    fn temp_dir(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("surface-test-{name}-{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        std::fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    fn write_hook(path: &Path, script: &str) {
+        std::fs::create_dir_all(path.parent().unwrap()).unwrap();
+        std::fs::write(path, format!("#!/bin/sh\n{script}\n")).unwrap();
+        std::fs::set_permissions(path, std::fs::Permissions::from_mode(0o755)).unwrap();
+    }
+
+    #[test]
+    fn env() {
+        let event = sdtx::Event::BaseConnection {
+            state: sdtx::event::BaseState::Detached,
+            device_type: sdtx::DeviceType::Ssh,
+            id: 0x0e,
+        };
+
+        let env = event_env(&PrettyEvent::new(event, None, &Models::builtin())).unwrap();
+
+        assert_eq!(env, vec![
+            ("SDTX_EVENT", "base-connection".to_owned()),
+            ("SDTX_BASE_STATE", "detached".to_owned()),
+            ("SDTX_BASE_TYPE", "ssh".to_owned()),
+            ("SDTX_BASE_ID", "0x0e".to_owned()),
+            ("SDTX_BASE_MODEL", "0x0e".to_owned()),
+        ]);
+
+        let event = sdtx::Event::Unknown { code: 0x42, data: vec![1, 2] };
+        let env = event_env(&PrettyEvent::new(event, None, &Models::builtin())).unwrap();
+
+        assert_eq!(env, vec![
+            ("SDTX_EVENT", "66".to_owned()),
+            ("SDTX_DATA", "[1,2]".to_owned()),
+        ]);
+    }
+
+    #[test]
+    fn find() {
+        let dir = temp_dir("find-hooks");
+
+        write_hook(&dir.join("20-second"), "true");
+        write_hook(&dir.join("10-first"), "true");
+        write_hook(&dir.join(".hidden"), "true");
+        write_hook(&dir.join("backup~"), "true");
+        std::fs::write(dir.join("not-executable"), "true").unwrap();
+        std::fs::create_dir(dir.join("subdir")).unwrap();
+
+        let hooks = find_hooks(&dir).unwrap();
+        let missing = find_hooks(&dir.join("missing")).unwrap();
+        std::fs::remove_dir_all(&dir).unwrap();
+
+        assert_eq!(hooks, vec![dir.join("10-first"), dir.join("20-second")]);
+        assert!(missing.is_empty());
+    }
+
+    #[test]
+    fn event_order() {
+        let dir = temp_dir("hook-order");
+        let out = dir.join("out");
+
+        // The hook of the first event takes longer than the one of the second.
+        write_hook(&dir.join("base-connection.d/hook"), &format!(
+            r#"[ "$SDTX_BASE_STATE" = detached ] && sleep 0.3; echo "$SDTX_BASE_STATE" >> {out:?}"#
+        ));
+
+        let mock = Mock::new();
+        let events = mock.events().unwrap();
+        let models = Models::builtin();
+
+        mock.detach();
+        mock.attach(sdtx::DeviceType::Ssh, 0x0e);
+
+        let mut hooks = Dispatcher::new(&dir, Duration::from_secs(5), true);
+        while let Ok(event) = events.recv_timeout(Duration::from_millis(100)) {
+            hooks.dispatch(&models, event.unwrap()).unwrap();
+        }
+        drop(hooks);
+
+        let out = std::fs::read_to_string(&out).unwrap();
+        std::fs::remove_dir_all(&dir).unwrap();
+
+        assert_eq!(out, "detached\nattached\n");
+    }
+}
@@ -0,0 +1,274 @@
+use std::ffi::OsString;
+use std::path::Path;
+
+use crate::sys;
+use crate::sys::pidfile::PidFile;
+
+use anyhow::{Context, Result};
+
+
+/// Processes currently inhibiting detachment. The latch is locked while this
+/// list is non-empty.
+#[cfg(not(test))]
+const INHIBITORS_FILE: &str = "/run/surface-control/dtx-inhibitors";
+
+/// Marker for a latch locked via 'lock'. Such a lock is kept until 'unlock',
+/// regardless of inhibitors coming and going.
+#[cfg(not(test))]
+const LOCKED_FILE: &str = "/run/surface-control/dtx-locked";
+
+#[cfg(test)]
+const INHIBITORS_FILE: &str = "/tmp/surface-control-test/dtx-inhibitors";
+
+#[cfg(test)]
+const LOCKED_FILE: &str = "/tmp/surface-control-test/dtx-locked";
+
+/// Serializes tests using the files above.
+#[cfg(test)]
+pub static TEST_FILES: std::sync::Mutex<()> = std::sync::Mutex::new(());
+
+
+pub fn command() -> clap::Command {
+    use clap::Arg;
+
+    clap::Command::new("inhibit")
+        .about("Lock the latch while running a command")
+        .arg(Arg::new("command")
+            .help("The command to run, followed by its arguments")
+            .value_parser(clap::value_parser!(OsString))
+            .num_args(1..)
+            .trailing_var_arg(true)
+            .allow_hyphen_values(true)
+            .required(true))
+}
+
+pub fn execute(m: &clap::ArgMatches) -> Result<()> {
+    let command: Vec<&OsString> = m.get_many("command").unwrap().collect();
+
+    sys::signal::catch_termination()
+        .context("Failed to set up signal handler")?;
+
+    let device = sys::dtx::open()
+        .context("Failed to open DTX device")?;
+
+    let inhibitor = Inhibitor::acquire(&*device)?;
+
+    let mut child = std::process::Command::new(command[0])
+        .args(&command[1..])
+        .spawn()
+        .with_context(|| format!("Failed to run {:?}", command[0]))?;
+
+    let status = crate::cli::wait_child(&mut child)?;
+
+    // Release before exiting, as process::exit does not run destructors.
+    if let Err(e) = inhibitor.release() {
+        eprintln!("Error: {e:#}");
+    }
+
+    std::process::exit(crate::cli::exit_code(status))
+}
+
+
+/// Lock the latch on behalf of the user. The EC does not report whether the
+/// latch is locked, so this is recorded for inhibitors to restore.
+pub fn lock(device: &dyn sys::dtx::Backend) -> Result<()> {
+    let path = Path::new(LOCKED_FILE);
+
+    PidFile::new(INHIBITORS_FILE).update(|_| -> Result<()> {
+        device.latch_lock()
+            .context("Failed to lock latch")?;
+
+        if let Some(dir) = path.parent() {
+            std::fs::create_dir_all(dir)
+                .with_context(|| format!("Failed to create directory {dir:?}"))?;
+        }
+
+        std::fs::write(path, "")
+            .with_context(|| format!("Failed to write {path:?}"))
+    }).context("Failed to read inhibitors")?
+}
+
+/// Undo a lock via [`lock`]. The latch is only unlocked if no inhibitors are
+/// running, otherwise it is unlocked once the last one exits. Returns the
+/// PIDs of the inhibitors keeping it locked.
+pub fn unlock(device: &dyn sys::dtx::Backend) -> Result<Vec<u32>> {
+    let path = Path::new(LOCKED_FILE);
+
+    PidFile::new(INHIBITORS_FILE).update(|pids| -> Result<Vec<u32>> {
+        match std::fs::remove_file(path) {
+            Err(e) if e.kind() != std::io::ErrorKind::NotFound => {
+                return Err(e).with_context(|| format!("Failed to remove {path:?}"));
+            },
+            _ => (),
+        }
+
+        if pids.is_empty() {
+            device.latch_unlock()
+                .context("Failed to unlock latch")?;
+        }
+
+        Ok(pids.clone())
+    }).context("Failed to read inhibitors")?
+}
+
+/// Whether the latch has been locked via 'lock'.
+pub fn is_locked() -> bool {
+    Path::new(LOCKED_FILE).exists()
+}
+
+
+/// Registration of this process as inhibitor, released on drop.
+///
+/// A lock via 'lock' is kept on release. If it is undone via 'unlock' while
+/// inhibitors are running, the last one to exit unlocks the latch.
+pub struct Inhibitor<'a> {
+    device: &'a dyn sys::dtx::Backend,
+    file: PidFile,
+    released: bool,
+}
+
+impl<'a> Inhibitor<'a> {
+    /// Register as inhibitor, locking the latch.
+    pub fn acquire(device: &'a dyn sys::dtx::Backend) -> Result<Self> {
+        let file = PidFile::new(INHIBITORS_FILE);
+        let pid = std::process::id();
+
+        file.update(|pids| -> Result<()> {
+            // Lock regardless of other inhibitors. The EC does not report
+            // whether it is still locked, e.g. after a reset.
+            device.latch_lock()
+                .context("Failed to lock latch")?;
+
+            pids.push(pid);
+            Ok(())
+        }).context("Failed to register inhibitor")??;
+
+        Ok(Inhibitor { device, file, released: false })
+    }
+
+    /// Unregister, restoring the previous latch state unless other inhibitors
+    /// still need it locked.
+    pub fn release(mut self) -> Result<()> {
+        self.released = true;
+        self.unregister()
+    }
+
+    fn unregister(&self) -> Result<()> {
+        let pid = std::process::id();
+
+        self.file.update(|pids| -> Result<()> {
+            pids.retain(|p| *p != pid);
+
+            // Other inhibitors are reference-counted via the list, so only
+            // a lock via 'lock' needs to be restored.
+            if pids.is_empty() && !is_locked() {
+                self.device.latch_unlock()
+                    .context("Failed to unlock latch")?;
+            }
+
+            Ok(())
+        }).context("Failed to unregister inhibitor")?
+    }
+}
+
+impl Drop for Inhibitor<'_> {
+    fn drop(&mut self) {
+        if self.released {
+            return;
+        }
+
+        if let Err(e) = self.unregister() {
+            eprintln!("Error: {e:#}");
+        }
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::sys::dtx::Mock;
+
+    fn lock_files() -> std::sync::MutexGuard<'static, ()> {
+        let guard = TEST_FILES.lock().unwrap_or_else(|e| e.into_inner());
+
+        let _ = std::fs::remove_file(INHIBITORS_FILE);
+        let _ = std::fs::remove_file(LOCKED_FILE);
+        guard
+    }
+
+    /// Register another process as inhibitor.
+    fn spawn_inhibitor() -> std::process::Child {
+        let child = std::process::Command::new("sleep").arg("60").spawn().unwrap();
+
+        PidFile::new(INHIBITORS_FILE).update(|pids| pids.push(child.id())).unwrap();
+        child
+    }
+
+    #[test]
+    fn inhibit() {
+        let _files = lock_files();
+        let mock = Mock::new();
+
+        let inhibitor = Inhibitor::acquire(&mock).unwrap();
+        assert!(mock.is_locked());
+
+        inhibitor.release().unwrap();
+        assert!(!mock.is_locked());
+
+        // Dropping releases as well.
+        let inhibitor = Inhibitor::acquire(&mock).unwrap();
+        drop(inhibitor);
+        assert!(!mock.is_locked());
+    }
+
+    #[test]
+    fn ref_counted() {
+        let _files = lock_files();
+        let mock = Mock::new();
+
+        let inhibitor = Inhibitor::acquire(&mock).unwrap();
+        let mut other = spawn_inhibitor();
+
+        inhibitor.release().unwrap();
+        assert!(mock.is_locked());
+
+        // Inhibitors that have exited without unregistering are dropped.
+        let _ = other.kill();
+        let _ = other.wait();
+
+        Inhibitor::acquire(&mock).unwrap().release().unwrap();
+        assert!(!mock.is_locked());
+    }
+
+    #[test]
+    fn lock_kept() {
+        let _files = lock_files();
+        let mock = Mock::new();
+
+        lock(&mock).unwrap();
+
+        Inhibitor::acquire(&mock).unwrap().release().unwrap();
+        assert!(mock.is_locked());
+
+        assert!(unlock(&mock).unwrap().is_empty());
+        assert!(!mock.is_locked());
+    }
+
+    #[test]
+    fn unlock_while_inhibited() {
+        let _files = lock_files();
+        let mock = Mock::new();
+
+        lock(&mock).unwrap();
+        let inhibitor = Inhibitor::acquire(&mock).unwrap();
+
+        assert_eq!(unlock(&mock).unwrap(), vec![std::process::id()]);
+        assert!(mock.is_locked());
+        assert!(!is_locked());
+
+        // The lock via 'lock' is gone, so the last inhibitor unlocks.
+        inhibitor.release().unwrap();
+        assert!(!mock.is_locked());
+    }
+}
@@ -0,0 +1,721 @@
+mod check;
+mod detach;
+mod events;
+mod guard;
+mod handshake;
+mod history;
+mod hold;
+mod hooks;
+mod inhibit;
+mod monitor;
+mod notify;
+mod prepare;
+mod record;
+mod replay;
+mod state;
+mod stats;
+mod timestamp;
+mod wait;
+
+use std::time::Duration;
+
+use crate::cli::Command as DynCommand;
+use crate::sys;
+
+use anyhow::{Context, Result};
+
+
+/// Interval at which heartbeats are sent to keep a detachment alive.
+const HEARTBEAT_INTERVAL: Duration = Duration::from_secs(1);
+
+
+pub struct Command;
+
+impl DynCommand for Command {
+    fn name(&self) -> &'static str {
+        "dtx"
+    }
+
+    fn build(&self) -> clap::Command {
+        clap::Command::new(self.name())
+            .about("Control the latch/dtx-system on the Surface Book 2")
+            .subcommand_required(true)
+            .arg_required_else_help(true)
+            .infer_subcommands(true)
+            .subcommand(clap::Command::new("lock")
+                .about("Lock the latch")
+                .display_order(1))
+            .subcommand(clap::Command::new("unlock")
+                .about("Unlock the latch")
+                .display_order(2))
+            .subcommand(clap::Command::new("request")
+                .about("Request latch-open or abort if already in progress")
+                .display_order(3))
+            .subcommand(clap::Command::new("confirm")
+                .about("Confirm latch-open if detachment in progress")
+                .display_order(4))
+            .subcommand(clap::Command::new("heartbeat")
+                .about("Send heartbeat if detachment in progress")
+                .display_order(5))
+            .subcommand(clap::Command::new("cancel")
+                .about("Cancel any detachment in progress")
+                .display_order(6))
+            .subcommand(clap::Command::new("get-base")
+                .about("Get information about the currently attached base")
+                .display_order(7))
+            .subcommand(clap::Command::new("get-devicemode")
+                .about("Query the current device operation mode")
+                .display_order(8))
+            .subcommand(clap::Command::new("get-latchstatus")
+                .about("Query the current latch status")
+                .display_order(9))
+            .subcommand(monitor::command()
+                .display_order(10))
+            .subcommand(detach::command()
+                .display_order(11))
+            .subcommand(check::command()
+                .display_order(12))
+            .subcommand(prepare::command()
+                .display_order(13))
+            .subcommand(wait::command()
+                .display_order(14))
+            .subcommand(hooks::command()
+                .display_order(15))
+            .subcommand(hold::command()
+                .display_order(16))
+            .subcommand(inhibit::command()
+                .display_order(17))
+            .subcommand(replay::command()
+                .display_order(18))
+            .subcommand(notify::command()
+                .display_order(19))
+            .subcommand(state::command()
+                .display_order(20))
+            .subcommand(guard::command()
+                .display_order(21))
+            .subcommand(stats::command()
+                .display_order(22))
+    }
+
+    fn execute(&self, m: &clap::ArgMatches) -> Result<()> {
+        match m.subcommand() {
+            Some(("lock",            m)) => self.lock(&*open()?, m),
+            Some(("unlock",          m)) => self.unlock(&*open()?, m),
+            Some(("request",         m)) => self.request(&*open()?, m),
+            Some(("confirm",         m)) => self.confirm(&*open()?, m),
+            Some(("heartbeat",       m)) => self.heartbeat(&*open()?, m),
+            Some(("cancel",          m)) => self.cancel(&*open()?, m),
+            Some(("get-base",        m)) => self.get_base_info(&*open()?, m),
+            Some(("get-devicemode",  m)) => self.get_device_mode(&*open()?, m),
+            Some(("get-latchstatus", m)) => self.get_latch_status(&*open()?, m),
+            Some(("monitor",         m)) => monitor::execute(m),
+            Some(("detach",          m)) => detach::execute(m),
+            Some(("check",           m)) => check::execute(m),
+            Some(("prepare",         m)) => prepare::execute(m),
+            Some(("wait",            m)) => wait::execute(m),
+            Some(("hooks",           m)) => hooks::execute(m),
+            Some(("hold",            m)) => hold::execute(m),
+            Some(("inhibit",         m)) => inhibit::execute(m),
+            Some(("replay",          m)) => replay::execute(m),
+            Some(("notify",          m)) => notify::execute(m),
+            Some(("state",           m)) => state::execute(m),
+            Some(("guard",           m)) => guard::execute(m),
+            Some(("stats",           m)) => stats::execute(m),
+            _                            => unreachable!(),
+        }
+    }
+}
+
+impl Command {
+    fn lock(&self, device: &dyn sys::dtx::Backend, m: &clap::ArgMatches) -> Result<()> {
+        inhibit::lock(device)?;
+
+        if !m.get_flag("quiet") {
+            println!("Clipboard latch locked");
+        }
+
+        Ok(())
+    }
+
+    fn unlock(&self, device: &dyn sys::dtx::Backend, m: &clap::ArgMatches) -> Result<()> {
+        let inhibitors = inhibit::unlock(device)?;
+
+        if !inhibitors.is_empty() {
+            let pids: Vec<String> = inhibitors.iter().map(|pid| pid.to_string()).collect();
+
+            eprintln!("Warning: Latch kept locked by running inhibitors (PID {}), unlocking once they exit",
+                pids.join(", "));
+
+        } else if !m.get_flag("quiet") {
+            println!("Clipboard latch unlocked");
+        }
+
+        Ok(())
+    }
+
+    fn request(&self, device: &dyn sys::dtx::Backend, m: &clap::ArgMatches) -> Result<()> {
+        device.latch_request()
+            .context("Failed to send latch request")?;
+
+        if !m.get_flag("quiet") {
+            println!("Clipboard latch request executed");
+        }
+
+        Ok(())
+    }
+
+    fn confirm(&self, device: &dyn sys::dtx::Backend, m: &clap::ArgMatches) -> Result<()> {
+        handshake::confirm(device)?;
+
+        if !m.get_flag("quiet") {
+            println!("Clipboard detachment confirmed");
+        }
+
+        Ok(())
+    }
+
+    fn heartbeat(&self, device: &dyn sys::dtx::Backend, m: &clap::ArgMatches) -> Result<()> {
+        device.latch_heartbeat()
+            .context("Failed to send heartbeat")?;
+
+        if !m.get_flag("quiet") {
+            println!("Clipboard detachment heartbeat sent");
+        }
+
+        Ok(())
+    }
+
+    fn cancel(&self, device: &dyn sys::dtx::Backend, m: &clap::ArgMatches) -> Result<()> {
+        device.latch_cancel()
+            .context("Failed to cancel detachment")?;
+
+        if !m.get_flag("quiet") {
+            println!("Clipboard detachment canceled");
+        }
+
+        Ok(())
+    }
+
+    fn get_base_info(&self, device: &dyn sys::dtx::Backend, m: &clap::ArgMatches) -> Result<()> {
+        let info = device.get_base_info()
+            .context("Failed to get base info")?;
+
+        let models = load_models();
+
+        let model = models.name(info.device_type, info.id);
+
+        if !m.get_flag("quiet") {
+            println!("State: {}", info.state);
+            println!("Type:  {}", info.device_type);
+            println!("ID:    {:#04x}", info.id);
+            println!("Model: {model}");
+
+        } else {
+            let text = serde_json::to_string(&PrettyBaseInfo { info, model })
+                .context("Failed to serialize data")?;
+
+            println!("{text}");
+        }
+
+        Ok(())
+    }
+
+    fn get_device_mode(&self, device: &dyn sys::dtx::Backend, _m: &clap::ArgMatches) -> Result<()> {
+        let mode = device.get_device_mode()
+            .context("Failed to get device mode")?;
+
+        println!("{mode}");
+        Ok(())
+    }
+
+    fn get_latch_status(&self, device: &dyn sys::dtx::Backend, _m: &clap::ArgMatches) -> Result<()> {
+        let status = device.get_latch_status()
+            .context("Failed to get latch status")?;
+
+        println!("{status}");
+        Ok(())
+    }
+}
+
+
+fn open() -> Result<Box<dyn sys::dtx::Backend>> {
+    sys::dtx::open().context("Failed to open DTX device")
+}
+
+/// Load the base models. A broken override file only results in a warning,
+/// falling back to the built-in models.
+pub fn load_models() -> sys::bases::Models {
+    sys::bases::Models::load()
+        .context("Failed to load base models")
+        .unwrap_or_else(|e| {
+            eprintln!("Warning: {e:#}, falling back to built-in ones");
+            sys::bases::Models::builtin()
+        })
+}
+
+/// Send a heartbeat. When reconnecting, errors are ignored as the device may
+/// only be gone temporarily, which the event receiver takes care of.
+fn send_heartbeat(device: &dyn sys::dtx::Backend, reconnect: bool) -> Result<()> {
+    match device.latch_heartbeat() {
+        Err(_) if reconnect => Ok(()),
+        result => result.context("Failed to send heartbeat"),
+    }
+}
+
+fn cancel_reason_str(reason: &sdtx::event::CancelReason) -> String {
+    use sdtx::event::CancelReason;
+
+    match reason {
+        CancelReason::Hardware(err) => err.to_string(),
+        CancelReason::Runtime(err)  => err.to_string(),
+        CancelReason::Unknown(x)    => format!("unknown reason {x:#04x}"),
+    }
+}
+
+fn event_type_str(event: &sdtx::Event) -> Option<&'static str> {
+    use sdtx::Event;
+
+    match event {
+        Event::Request               => Some("request"),
+        Event::Cancel { .. }         => Some("cancel"),
+        Event::BaseConnection { .. } => Some("base-connection"),
+        Event::LatchStatus { .. }    => Some("latch-status"),
+        Event::DeviceMode { .. }     => Some("device-mode"),
+        Event::Unknown { .. }        => None,
+    }
+}
+
+fn base_state_str(state: sdtx::BaseState) -> &'static str {
+    match state {
+        sdtx::BaseState::Attached    => "attached",
+        sdtx::BaseState::Detached    => "detached",
+        sdtx::BaseState::NotFeasible => "not-feasible",
+    }
+}
+
+fn device_mode_str(mode: sdtx::DeviceMode) -> &'static str {
+    match mode {
+        sdtx::DeviceMode::Tablet => "tablet",
+        sdtx::DeviceMode::Laptop => "laptop",
+        sdtx::DeviceMode::Studio => "studio",
+    }
+}
+
+fn latch_status_str(status: sdtx::LatchStatus) -> String {
+    use sdtx::LatchStatus;
+
+    match status {
+        LatchStatus::Closed     => "closed".into(),
+        LatchStatus::Opened     => "opened".into(),
+        LatchStatus::Error(err) => hardware_error_str(err).to_string(),
+    }
+}
+
+fn hardware_error_str(err: sdtx::HardwareError) -> ErrorStr {
+    use sdtx::{HardwareError, uapi};
+
+    match err {
+        HardwareError::FailedToOpen       => ErrorStr::Name("failed-to-open"),
+        HardwareError::FailedToRemainOpen => ErrorStr::Name("failed-to-remain-open"),
+        HardwareError::FailedToClose      => ErrorStr::Name("failed-to-close"),
+        HardwareError::Unknown(x)         => ErrorStr::Code(x as u16 | uapi::SDTX_CATEGORY_HARDWARE_ERROR),
+    }
+}
+
+fn runtime_error_str(err: sdtx::RuntimeError) -> ErrorStr {
+    use sdtx::{RuntimeError, uapi};
+
+    match err {
+        RuntimeError::NotFeasible => ErrorStr::Name("not-feasible"),
+        RuntimeError::Timeout     => ErrorStr::Name("timeout"),
+        RuntimeError::Unknown(x)  => ErrorStr::Code(x as u16 | uapi::SDTX_CATEGORY_RUNTIME_ERROR),
+    }
+}
+
+
+/// Name of an error as used in machine-readable output. Unknown errors are
+/// given by their code, including the category bits.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+enum ErrorStr {
+    Name(&'static str),
+    Code(u16),
+}
+
+impl std::fmt::Display for ErrorStr {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ErrorStr::Name(name) => write!(f, "{name}"),
+            ErrorStr::Code(code) => write!(f, "{code:#06x}"),
+        }
+    }
+}
+
+impl serde::Serialize for ErrorStr {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: serde::Serializer
+    {
+        match self {
+            ErrorStr::Name(name) => serializer.serialize_str(name),
+            ErrorStr::Code(code) => serializer.serialize_u16(*code),
+        }
+    }
+}
+
+/// The base state reported by an event, as returned by a query.
+fn event_base_state(state: sdtx::event::BaseState) -> Option<sdtx::BaseState> {
+    use sdtx::event::BaseState;
+
+    match state {
+        BaseState::Attached    => Some(sdtx::BaseState::Attached),
+        BaseState::Detached    => Some(sdtx::BaseState::Detached),
+        BaseState::NotFeasible => Some(sdtx::BaseState::NotFeasible),
+        BaseState::Unknown(_)  => None,
+    }
+}
+
+/// The device mode reported by an event, as returned by a query.
+fn event_device_mode(mode: sdtx::event::DeviceMode) -> Option<sdtx::DeviceMode> {
+    use sdtx::event::DeviceMode;
+
+    match mode {
+        DeviceMode::Tablet     => Some(sdtx::DeviceMode::Tablet),
+        DeviceMode::Laptop     => Some(sdtx::DeviceMode::Laptop),
+        DeviceMode::Studio     => Some(sdtx::DeviceMode::Studio),
+        DeviceMode::Unknown(_) => None,
+    }
+}
+
+/// The latch status reported by an event, as returned by a query.
+fn event_latch_status(status: sdtx::event::LatchStatus) -> Option<sdtx::LatchStatus> {
+    use sdtx::event::LatchStatus;
+
+    match status {
+        LatchStatus::Closed     => Some(sdtx::LatchStatus::Closed),
+        LatchStatus::Opened     => Some(sdtx::LatchStatus::Opened),
+        LatchStatus::Error(err) => Some(sdtx::LatchStatus::Error(err)),
+        LatchStatus::Unknown(_) => None,
+    }
+}
+
+
+struct PrettyBaseInfo {
+    info: sdtx::BaseInfo,
+    model: String,
+}
+
+impl serde::Serialize for PrettyBaseInfo {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: serde::Serializer
+    {
+        use serde::ser::SerializeStruct;
+
+        let mut s = serializer.serialize_struct("BaseInfo", 4)?;
+
+        s.serialize_field("state", base_state_str(self.info.state))?;
+
+        match self.info.device_type {
+            sdtx::DeviceType::Hid        => s.serialize_field("type", "hid"),
+            sdtx::DeviceType::Ssh        => s.serialize_field("type", "ssh"),
+            sdtx::DeviceType::Unknown(x) => s.serialize_field("type", &x),
+        }?;
+
+        s.serialize_field("id", &self.info.id)?;
+        s.serialize_field("model", &self.model)?;
+        s.end()
+    }
+}
+
+
+struct PrettyEvent {
+    event: sdtx::Event,
+    time: Option<timestamp::Timestamp>,
+    model: Option<String>,
+}
+
+impl PrettyEvent {
+    fn new(event: sdtx::Event, time: Option<timestamp::Timestamp>, models: &sys::bases::Models) -> Self {
+        let model = match &event {
+            sdtx::Event::BaseConnection { device_type, id, .. } => Some(models.name(*device_type, *id)),
+            _ => None,
+        };
+
+        PrettyEvent { event, time, model }
+    }
+}
+
+impl serde::Serialize for PrettyEvent {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: serde::Serializer
+    {
+        use serde::ser::SerializeStruct;
+        use sdtx::{DeviceType, Event};
+        use sdtx::event::{BaseState, CancelReason, DeviceMode, LatchStatus};
+
+        let time_len = if self.time.is_some() { 1 } else { 0 };
+
+        match &self.event {
+            Event::Request => {
+                let mut s = serializer.serialize_struct("Event", 1 + time_len)?;
+                s.serialize_field("type", "request")?;
+                if let Some(time) = &self.time {
+                    s.serialize_field("time", time)?;
+                }
+                s.end()
+            },
+
+            Event::Cancel { reason } => {
+                let mut s = serializer.serialize_struct("Event", 2 + time_len)?;
+                s.serialize_field("type", "cancel")?;
+                if let Some(time) = &self.time {
+                    s.serialize_field("time", time)?;
+                }
+
+                match reason {
+                    CancelReason::Hardware(err) => s.serialize_field("reason", &hardware_error_str(*err)),
+                    CancelReason::Runtime(err)  => s.serialize_field("reason", &runtime_error_str(*err)),
+                    CancelReason::Unknown(x)    => s.serialize_field("reason", x),
+                }?;
+
+                s.end()
+            },
+
+            Event::BaseConnection { state, device_type, id } => {
+                let model_len = if self.model.is_some() { 1 } else { 0 };
+
+                let mut s = serializer.serialize_struct("Event", 4 + time_len + model_len)?;
+                s.serialize_field("type", "base-connection")?;
+                if let Some(time) = &self.time {
+                    s.serialize_field("time", time)?;
+                }
+
+                match state {
+                    BaseState::Attached    => s.serialize_field("state", "attached"),
+                    BaseState::Detached    => s.serialize_field("state", "detached"),
+                    BaseState::NotFeasible => s.serialize_field("state", "not-feasible"),
+                    BaseState::Unknown(x)  => s.serialize_field("state", x),
+                }?;
+
+                match device_type {
+                    DeviceType::Hid        => s.serialize_field("device-type", "hid"),
+                    DeviceType::Ssh        => s.serialize_field("device-type", "ssh"),
+                    DeviceType::Unknown(x) => s.serialize_field("device-type", &x),
+                }?;
+
+                s.serialize_field("id", id)?;
+                if let Some(model) = &self.model {
+                    s.serialize_field("model", model)?;
+                }
+                s.end()
+            },
+
+            Event::LatchStatus { status } => {
+                let mut s = serializer.serialize_struct("Event", 2 + time_len)?;
+                s.serialize_field("type", "latch-status")?;
+                if let Some(time) = &self.time {
+                    s.serialize_field("time", time)?;
+                }
+
+                match status {
+                    LatchStatus::Closed     => s.serialize_field("status", "closed"),
+                    LatchStatus::Opened     => s.serialize_field("status", "opened"),
+                    LatchStatus::Error(err) => s.serialize_field("status", &hardware_error_str(*err)),
+                    LatchStatus::Unknown(x) => s.serialize_field("status", x),
+                }?;
+
+                s.end()
+            },
+
+            Event::DeviceMode { mode } => {
+                let mut s = serializer.serialize_struct("Event", 2 + time_len)?;
+                s.serialize_field("type", "device-mode")?;
+                if let Some(time) = &self.time {
+                    s.serialize_field("time", time)?;
+                }
+
+                match mode {
+                    DeviceMode::Tablet     => s.serialize_field("mode", "tablet"),
+                    DeviceMode::Laptop     => s.serialize_field("mode", "laptop"),
+                    DeviceMode::Studio     => s.serialize_field("mode", "studio"),
+                    DeviceMode::Unknown(x) => s.serialize_field("mode", x),
+                }?;
+
+                s.end()
+            },
+
+            Event::Unknown { code, data } => {
+                let mut s = serializer.serialize_struct("Event", 2 + time_len)?;
+                s.serialize_field("type", code)?;
+                if let Some(time) = &self.time {
+                    s.serialize_field("time", time)?;
+                }
+                s.serialize_field("data", data)?;
+                s.end()
+            },
+        }
+    }
+}
+
+impl std::fmt::Display for PrettyEvent {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        use sdtx::{DeviceType, Event};
+        use sdtx::event::{BaseState, CancelReason, DeviceMode, LatchStatus};
+
+        match &self.event {
+            Event::Request => {
+                write!(f, "Request")
+            },
+
+            Event::Cancel { reason } => {
+                write!(f, "Cancel         {{ Reason: ")?;
+
+                match reason {
+                    CancelReason::Hardware(err) => write!(f, "\"{err}\""),
+                    CancelReason::Runtime(err)  => write!(f, "\"{err}\""),
+                    CancelReason::Unknown(err)  => write!(f, "{err:#04x}"),
+                }?;
+
+                write!(f, " }}")
+            },
+
+            Event::BaseConnection { state, device_type, id } => {
+                write!(f, "BaseConnection {{ State: ")?;
+
+                match state {
+                    BaseState::Detached    => write!(f, "Detached"),
+                    BaseState::Attached    => write!(f, "Attached"),
+                    BaseState::NotFeasible => write!(f, "NotFeasible"),
+                    BaseState::Unknown(x)  => write!(f, "{x:#04x}"),
+                }?;
+
+                write!(f, ", DeviceType: ")?;
+
+                match device_type {
+                    DeviceType::Hid        => write!(f, "Hid"),
+                    DeviceType::Ssh        => write!(f, "Ssh"),
+                    DeviceType::Unknown(x) => write!(f, "{x:#04x}"),
+                }?;
+
+                write!(f, ", Id: {id:#04x}")?;
+
+                if let Some(model) = &self.model {
+                    write!(f, ", Model: \"{model}\"")?;
+                }
+
+                write!(f, " }}")
+            },
+
+            Event::LatchStatus { status } => {
+                write!(f, "LatchStatus    {{ Status: ")?;
+
+                match status {
+                    LatchStatus::Closed     => write!(f, "Closed"),
+                    LatchStatus::Opened     => write!(f, "Opened"),
+                    LatchStatus::Error(err) => write!(f, "\"Error: {err}\""),
+                    LatchStatus::Unknown(x) => write!(f, "{x:#04x}"),
+                }?;
+
+                write!(f, " }}")
+            },
+
+            Event::DeviceMode { mode } => {
+                write!(f, "DeviceMode     {{ Status: ")?;
+
+                match mode {
+                    DeviceMode::Tablet     => write!(f, "Tablet"),
+                    DeviceMode::Laptop     => write!(f, "Laptop"),
+                    DeviceMode::Studio     => write!(f, "Studio"),
+                    DeviceMode::Unknown(x) => write!(f, "{x:#04x}"),
+                }?;
+
+                write!(f, " }}")
+            },
+
+            Event::Unknown { code, data } => {
+                write!(f, "Unknown        {{ Code: {code:#04x}, Data: {data:02x?} }}")
+            },
+        }
+    }
+}
+
+
+/// Synthetic marker reported after reconnecting to the device, with the state
+/// re-read from it.
+struct PrettyReconnect {
+    snapshot: events::Snapshot,
+    time: Option<timestamp::Timestamp>,
+    model: String,
+}
+
+impl PrettyReconnect {
+    fn new(snapshot: events::Snapshot, time: Option<timestamp::Timestamp>, models: &sys::bases::Models) -> Self {
+        let model = models.name(snapshot.base.device_type, snapshot.base.id);
+        PrettyReconnect { snapshot, time, model }
+    }
+}
+
+impl serde::Serialize for PrettyReconnect {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: serde::Serializer
+    {
+        use serde::ser::SerializeStruct;
+
+        let time_len = if self.time.is_some() { 1 } else { 0 };
+
+        let mut s = serializer.serialize_struct("Reconnect", 7 + time_len)?;
+        s.serialize_field("type", "reconnected")?;
+        if let Some(time) = &self.time {
+            s.serialize_field("time", time)?;
+        }
+
+        s.serialize_field("state", base_state_str(self.snapshot.base.state))?;
+
+        match self.snapshot.base.device_type {
+            sdtx::DeviceType::Hid        => s.serialize_field("device-type", "hid"),
+            sdtx::DeviceType::Ssh        => s.serialize_field("device-type", "ssh"),
+            sdtx::DeviceType::Unknown(x) => s.serialize_field("device-type", &x),
+        }?;
+
+        s.serialize_field("id", &self.snapshot.base.id)?;
+        s.serialize_field("model", &self.model)?;
+        s.serialize_field("mode", device_mode_str(self.snapshot.mode))?;
+        s.serialize_field("status", &latch_status_str(self.snapshot.latch))?;
+        s.end()
+    }
+}
+
+impl std::fmt::Display for PrettyReconnect {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let base = &self.snapshot.base;
+
+        write!(f, "Reconnected    {{ State: {}, DeviceType: ", base_state_str(base.state))?;
+
+        match base.device_type {
+            sdtx::DeviceType::Hid        => write!(f, "Hid"),
+            sdtx::DeviceType::Ssh        => write!(f, "Ssh"),
+            sdtx::DeviceType::Unknown(x) => write!(f, "{x:#04x}"),
+        }?;
+
+        write!(f, ", Id: {:#04x}, Model: \"{}\"", base.id, self.model)?;
+        write!(f, ", Mode: {}", device_mode_str(self.snapshot.mode))?;
+        write!(f, ", Latch: {} }}", latch_status_str(self.snapshot.latch))
+    }
+}
+
+
+/// Parse a `dtx` subcommand line and return the matches of the subcommand,
+/// including global options.
+#[cfg(test)]
+fn matches(args: &[&str]) -> clap::ArgMatches {
+    let cli = crate::cli::build().cli();
+    let args = ["surface", "dtx"].iter().chain(args);
+
+    let m = cli.try_get_matches_from(args).unwrap();
+    let (_, m) = m.subcommand().unwrap();
+    let (_, m) = m.subcommand().unwrap();
+
+    m.clone()
+}
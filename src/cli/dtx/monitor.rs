@@ -0,0 +1,281 @@
+use std::path::PathBuf;
+use std::time::{Duration, Instant};
+
+use crate::cli::dtx::{event_type_str, load_models, PrettyEvent, PrettyReconnect};
+use crate::cli::dtx::events::{self, EventReceiver, Received};
+use crate::cli::dtx::handshake::{Phase, Tracker};
+use crate::cli::dtx::history::{EventLog, LOG_FILE};
+use crate::cli::dtx::record::Recorder;
+use crate::cli::dtx::timestamp::{Format, Timestamp};
+use crate::sys;
+
+use anyhow::{Context, Result};
+
+
+const EVENT_TYPES: [&str; 5] = ["request", "cancel", "base-connection", "latch-status", "device-mode"];
+
+
+pub fn command() -> clap::Command {
+    use clap::Arg;
+
+    clap::Command::new("monitor")
+        .about("Monitor DTX events")
+        .arg(Arg::new("filter")
+            .help("Only show events of the given types")
+            .long("filter")
+            .value_name("TYPES")
+            .value_parser(EVENT_TYPES)
+            .value_delimiter(',')
+            .action(clap::ArgAction::Append))
+        .arg(Arg::new("timestamps")
+            .help("Prefix events with a timestamp (monotonic: seconds since start)")
+            .long("timestamps")
+            .value_name("FORMAT")
+            .value_parser(["monotonic", "rfc3339"])
+            .num_args(0..=1)
+            .default_missing_value("monotonic"))
+        .arg(Arg::new("count")
+            .help("Exit after the given number of events")
+            .long("count")
+            .short('n')
+            .value_name("N")
+            .value_parser(clap::value_parser!(u64).range(1..)))
+        .arg(Arg::new("timeout")
+            .help("Exit after the given number of seconds")
+            .long("timeout")
+            .value_name("SECS")
+            .value_parser(clap::value_parser!(u64)))
+        .arg(Arg::new("record")
+            .help("Record events with their timing to the given file, for use with 'replay'")
+            .long("record")
+            .value_name("FILE")
+            .value_parser(clap::value_parser!(PathBuf)))
+        .arg(Arg::new("phases")
+            .help("Also report changes of the detachment handshake phase, see 'state'")
+            .long("phases")
+            .action(clap::ArgAction::SetTrue))
+        .arg(events::reconnect_arg())
+}
+
+pub fn execute(m: &clap::ArgMatches) -> Result<()> {
+    let device = sys::dtx::open()
+        .context("Failed to open DTX device")?;
+
+    run(&*device, m)
+}
+
+pub fn run(device: &dyn sys::dtx::Backend, m: &clap::ArgMatches) -> Result<()> {
+    let quiet = m.get_flag("quiet");
+    let count: Option<u64> = m.get_one("count").copied();
+    let timeout: Option<u64> = m.get_one("timeout").copied();
+    let filter: Option<Vec<&String>> = m.get_many("filter").map(|f| f.collect());
+
+    let timestamps = m.get_one::<String>("timestamps").map(|f| match f.as_str() {
+        "rfc3339" => Format::Rfc3339,
+        _         => Format::Monotonic,
+    });
+
+    // The JSON output always carries a timestamp, default to wall-clock time.
+    let format = match (quiet, timestamps) {
+        (_, Some(format)) => Some(format),
+        (true, None)      => Some(Format::Rfc3339),
+        (false, None)     => None,
+    };
+
+    let mut recorder = match m.get_one::<PathBuf>("record") {
+        Some(path) => Some(Recorder::create(path)?),
+        None       => None,
+    };
+
+    let models = load_models();
+
+    let events = EventReceiver::open(device)?
+        .reconnecting(m.get_flag("reconnect"));
+
+    let mut log = EventLog::new(LOG_FILE);
+
+    let mut tracker = if m.get_flag("phases") {
+        Some(Tracker::from_device(device)?)
+    } else {
+        None
+    };
+
+    let start = Instant::now();
+    let deadline = timeout.map(|t| start + Duration::from_secs(t));
+
+    let mut seen = 0;
+    while count.is_none_or(|count| seen < count) {
+        let received = match deadline {
+            Some(deadline) => match events.recv_any_deadline(deadline)? {
+                Some(received) => received,
+                None           => break,
+            },
+            None => events.recv_any()?,
+        };
+
+        let time = format.map(|f| Timestamp::now(f, start));
+
+        // The marker is always shown, but neither counted nor recorded as it
+        // is not an actual event.
+        let event = match received {
+            Received::Event(event) => log.write(event)?,
+            Received::Reconnected(snapshot) => {
+                print_reconnect(quiet, &PrettyReconnect::new(snapshot, time, &models))?;
+
+                if let Some(tracker) = &mut tracker {
+                    if tracker.resync(snapshot.latch) {
+                        print_phase(quiet, tracker.phase(), time)?;
+                    }
+                }
+
+                continue;
+            },
+        };
+
+        // The tracker needs to see all events, regardless of the filter.
+        let phase = match &mut tracker {
+            Some(tracker) => tracker.update(&event).then(|| tracker.phase().clone()),
+            None          => None,
+        };
+
+        let shown = filter.as_ref().is_none_or(|filter| {
+            let ty = event_type_str(&event);
+            filter.iter().any(|f| Some(f.as_str()) == ty)
+        });
+
+        if shown {
+            let event = match &mut recorder {
+                Some(recorder) => recorder.write(event, start.elapsed())?,
+                None           => event,
+            };
+
+            print(quiet, &PrettyEvent::new(event, time, &models))?;
+            seen += 1;
+        }
+
+        if let Some(phase) = phase {
+            print_phase(quiet, &phase, time)?;
+        }
+    }
+
+    Ok(())
+}
+
+pub fn print(quiet: bool, event: &PrettyEvent) -> Result<()> {
+    if !quiet {
+        match &event.time {
+            Some(time) => println!("[{time}] {event}"),
+            None       => println!("{event}"),
+        }
+
+    } else {
+        let text = serde_json::to_string(event)
+            .context("Failed to serialize data")?;
+
+        println!("{text}");
+    }
+
+    Ok(())
+}
+
+fn print_reconnect(quiet: bool, marker: &PrettyReconnect) -> Result<()> {
+    if !quiet {
+        match &marker.time {
+            Some(time) => println!("[{time}] {marker}"),
+            None       => println!("{marker}"),
+        }
+
+    } else {
+        let text = serde_json::to_string(marker)
+            .context("Failed to serialize data")?;
+
+        println!("{text}");
+    }
+
+    Ok(())
+}
+
+fn print_phase(quiet: bool, phase: &Phase, time: Option<Timestamp>) -> Result<()> {
+    if !quiet {
+        let reason = phase.reason()
+            .map(|r| format!(", Reason: \"{r}\""))
+            .unwrap_or_default();
+
+        match time {
+            Some(time) => println!("[{time}] Phase          {{ Name: {}{reason} }}", phase.name()),
+            None       => println!("Phase          {{ Name: {}{reason} }}", phase.name()),
+        }
+
+    } else {
+        let text = serde_json::json!({
+            "type": "phase",
+            "time": time,
+            "phase": phase.name(),
+            "reason": phase.reason(),
+        });
+
+        println!("{text}");
+    }
+
+    Ok(())
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cli::dtx::matches;
+    use crate::sys::dtx::Mock;
+
+    #[test]
+    fn count() {
+        let mock = Mock::new();
+
+        let user = mock.clone();
+        std::thread::spawn(move || {
+            std::thread::sleep(Duration::from_millis(100));
+            user.press();
+            user.detach();
+        });
+
+        let m = matches(&["monitor", "-q", "--filter", "base-connection,request", "-n", "2", "--timeout", "2"]);
+        run(&mock, &m).unwrap();
+    }
+
+    #[test]
+    fn record() {
+        let mock = Mock::new();
+        let path = std::env::temp_dir().join(format!("surface-test-record-{}", std::process::id()));
+
+        let user = mock.clone();
+        std::thread::spawn(move || {
+            std::thread::sleep(Duration::from_millis(100));
+            user.press();
+            user.detach();
+        });
+
+        let m = matches(&["monitor", "-q", "-n", "3", "--timeout", "2", "--record", path.to_str().unwrap()]);
+        run(&mock, &m).unwrap();
+
+        let events: Vec<_> = crate::cli::dtx::record::load(&path).unwrap()
+            .into_iter()
+            .map(|(_, event)| event)
+            .collect();
+
+        std::fs::remove_file(&path).unwrap();
+
+        assert!(matches!(events[0], sdtx::Event::Request));
+        assert!(matches!(events[1], sdtx::Event::BaseConnection { .. }));
+        assert!(matches!(events[2], sdtx::Event::DeviceMode { .. }));
+    }
+
+    #[test]
+    fn timeout() {
+        let mock = Mock::new();
+        let start = Instant::now();
+
+        run(&mock, &matches(&["monitor", "-q", "--timeout", "1"])).unwrap();
+
+        assert!(start.elapsed() >= Duration::from_secs(1));
+    }
+}
@@ -0,0 +1,221 @@
+use std::time::Duration;
+
+use crate::cli::dtx::{cancel_reason_str, load_models};
+use crate::cli::dtx::events::{self, EventReceiver, Received};
+use crate::cli::dtx::handshake;
+use crate::cli::dtx::history::{EventLog, LOG_FILE};
+use crate::sys;
+use crate::sys::bases::Models;
+use crate::sys::notifications::{Notification, Notifications, Signal, Urgency};
+
+use anyhow::{Context, Result};
+
+
+const APP_NAME: &str = "Surface DTX";
+const POLL_INTERVAL: Duration = Duration::from_millis(50);
+
+const ACTION_CONFIRM: &str = "confirm";
+const ACTION_CANCEL: &str = "cancel";
+
+
+pub fn command() -> clap::Command {
+    clap::Command::new("notify")
+        .about("Show desktop notifications for DTX events, with actions to confirm or cancel")
+        .arg(events::reconnect_arg())
+}
+
+pub fn execute(m: &clap::ArgMatches) -> Result<()> {
+    let device = sys::dtx::open()
+        .context("Failed to open DTX device")?;
+
+    run(&*device, m)
+}
+
+pub fn run(device: &dyn sys::dtx::Backend, m: &clap::ArgMatches) -> Result<()> {
+    let quiet = m.get_flag("quiet");
+
+    let models = load_models();
+    let mut notifier = Notifier::connect()?;
+
+    let events = EventReceiver::open(device)?
+        .reconnecting(m.get_flag("reconnect"));
+
+    let mut log = EventLog::new(LOG_FILE);
+
+    loop {
+        match events.recv_any_timeout(POLL_INTERVAL)? {
+            Some(Received::Event(event)) => {
+                let event = log.write(event)?;
+                notifier.show(&models, &event)?;
+            },
+            Some(Received::Reconnected(_)) => {
+                // We may have missed the end of the current detachment, so
+                // don't act on its notification anymore.
+                notifier.current = 0;
+            },
+            None => (),
+        }
+
+        let signals = notifier.notifications.poll(POLL_INTERVAL)
+            .context("Failed to receive notification signals")?;
+
+        for signal in signals {
+            match signal {
+                Signal::ActionInvoked { id, action } if id == notifier.current => {
+                    let (result, msg) = match action.as_str() {
+                        ACTION_CONFIRM => (
+                            handshake::confirm(device),
+                            "Clipboard detachment confirmed",
+                        ),
+                        ACTION_CANCEL => (
+                            device.latch_cancel().context("Failed to cancel detachment"),
+                            "Clipboard detachment canceled",
+                        ),
+                        _ => continue,
+                    };
+
+                    match result {
+                        Ok(()) if !quiet => println!("{msg}"),
+                        Ok(())           => (),
+                        Err(e)           => eprintln!("Error: {e:#}"),
+                    }
+                },
+                Signal::Closed { id } if id == notifier.current => {
+                    notifier.current = 0;
+                },
+                _ => (),
+            }
+        }
+    }
+}
+
+
+/// Shows the notifications for a stream of events.
+pub struct Notifier {
+    notifications: Notifications,
+
+    /// The notification for the current detachment, updated in place as the
+    /// handshake progresses.
+    current: u32,
+}
+
+impl Notifier {
+    pub fn connect() -> Result<Self> {
+        let notifications = Notifications::connect(APP_NAME)
+            .context("Failed to connect to session bus")?;
+
+        Ok(Notifier { notifications, current: 0 })
+    }
+
+    /// Show the notification for the given event, if any.
+    pub fn show(&mut self, models: &Models, event: &sdtx::Event) -> Result<()> {
+        if let Some((notification, last)) = notification(models, event) {
+            let id = self.notifications.notify(self.current, &notification)
+                .context("Failed to send notification")?;
+
+            self.current = if last { 0 } else { id };
+        }
+
+        Ok(())
+    }
+}
+
+
+/// The notification for the given event, if any, and whether it concludes
+/// the current detachment.
+fn notification(models: &Models, event: &sdtx::Event) -> Option<(Notification, bool)> {
+    use sdtx::Event;
+    use sdtx::event::{BaseState, CancelReason, LatchStatus};
+
+    let notification = match event {
+        Event::Request => {
+            let mut n = Notification::new(
+                "Detach requested \u{2014} close GPU apps",
+                "Close all applications using the discrete GPU, then confirm to open the latch.",
+            );
+            n.urgency = Urgency::Critical;
+            n.actions = vec![
+                (ACTION_CONFIRM.into(), "Detach".into()),
+                (ACTION_CANCEL.into(), "Cancel".into()),
+            ];
+            n.timeout = Some(Duration::ZERO);
+
+            (n, false)
+        },
+        Event::Cancel { reason } => {
+            let summary = format!("Detach canceled: {}", cancel_reason_str(reason));
+            (Notification::new(summary, ""), true)
+        },
+        Event::LatchStatus { status: LatchStatus::Opened } => {
+            (Notification::new("Latch opened", "The clipboard can now be detached."), false)
+        },
+        Event::LatchStatus { status: LatchStatus::Error(err) } => {
+            let reason = cancel_reason_str(&CancelReason::Hardware(*err));
+
+            let mut n = Notification::new(format!("Latch error: {reason}"), "");
+            n.urgency = Urgency::Critical;
+
+            (n, true)
+        },
+        Event::BaseConnection { state: BaseState::Attached, device_type, id } => {
+            let mut n = Notification::new("Clipboard attached", models.name(*device_type, *id));
+            n.urgency = Urgency::Low;
+
+            (n, true)
+        },
+        Event::BaseConnection { state: BaseState::Detached, .. } => {
+            let mut n = Notification::new("Clipboard detached", "");
+            n.urgency = Urgency::Low;
+
+            (n, true)
+        },
+        _ => return None,
+    };
+
+    Some(notification)
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use sdtx::Event;
+    use sdtx::event::{BaseState, CancelReason, LatchStatus};
+    use sdtx::{DeviceType, HardwareError, RuntimeError};
+
+    #[test]
+    fn detachment() {
+        let models = Models::builtin();
+
+        let (request, last) = notification(&models, &Event::Request).unwrap();
+        assert!(!last);
+        assert_eq!(request.urgency, Urgency::Critical);
+        assert_eq!(request.actions.len(), 2);
+
+        let (opened, last) = notification(&models, &Event::LatchStatus { status: LatchStatus::Opened }).unwrap();
+        assert!(!last);
+        assert_eq!(opened.summary, "Latch opened");
+
+        let event = Event::BaseConnection { state: BaseState::Detached, device_type: DeviceType::Ssh, id: 0x0e };
+        let (detached, last) = notification(&models, &event).unwrap();
+        assert!(last);
+        assert_eq!(detached.summary, "Clipboard detached");
+    }
+
+    #[test]
+    fn canceled() {
+        let models = Models::builtin();
+
+        let event = Event::Cancel { reason: CancelReason::Runtime(RuntimeError::Timeout) };
+        let (n, last) = notification(&models, &event).unwrap();
+        assert!(last);
+        assert!(n.summary.starts_with("Detach canceled: "), "{}", n.summary);
+
+        let event = Event::LatchStatus { status: LatchStatus::Error(HardwareError::FailedToOpen) };
+        let (n, last) = notification(&models, &event).unwrap();
+        assert!(last);
+        assert_eq!(n.urgency, Urgency::Critical);
+
+        assert!(notification(&models, &Event::Unknown { code: 0x42, data: vec![] }).is_none());
+    }
+}
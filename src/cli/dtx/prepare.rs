@@ -0,0 +1,107 @@
+use crate::sys;
+use crate::sys::storage::Disk;
+
+use anyhow::{Context, Result};
+
+
+pub fn command() -> clap::Command {
+    use clap::Arg;
+
+    clap::Command::new("prepare")
+        .about("Unmount and power off storage devices in the base before detaching")
+        .arg(Arg::new("hub")
+            .help("USB hub connecting the base by sysfs name (e.g. '1-3'), instead of auto-detection")
+            .long("hub")
+            .value_name("NAME")
+            .action(clap::ArgAction::Append))
+}
+
+pub fn execute(m: &clap::ArgMatches) -> Result<()> {
+    let quiet = m.get_flag("quiet");
+    let hubs: Vec<String> = m.get_many("hub")
+        .map(|h| h.cloned().collect())
+        .unwrap_or_default();
+
+    let hubs = sys::base::find_usb_hubs(&hubs)
+        .context("Failed to look up base USB hub")?;
+
+    if hubs.is_empty() {
+        anyhow::bail!("Base USB hub not found, specify it via '--hub'");
+    }
+
+    let blocks = sys::base::Base::new(hubs)
+        .devices(Some("block"))
+        .context("Failed to look up base block devices")?;
+
+    let disks = Disk::from_devices(blocks);
+    let udisks = sys::storage::udisks_available();
+
+    nix::unistd::sync();
+
+    let mut released = Vec::new();
+    let mut failed = Vec::new();
+
+    for disk in &disks {
+        let name = disk.node()
+            .map(|n| n.display().to_string())
+            .unwrap_or_else(|| disk.name());
+
+        match release(disk, udisks) {
+            Ok(()) => {
+                if !quiet {
+                    println!("Released {name}");
+                }
+                released.push(name);
+            },
+            Err(e) => {
+                if !quiet {
+                    println!("Failed to release {name}: {e:#}");
+                }
+                failed.push((name, format!("{e:#}")));
+            },
+        }
+    }
+
+    if quiet {
+        let failed: Vec<_> = failed.iter()
+            .map(|(device, reason)| serde_json::json!({ "device": device, "reason": reason }))
+            .collect();
+
+        let text = serde_json::json!({ "released": released, "failed": failed });
+        let text = serde_json::to_string(&text)
+            .context("Failed to serialize data")?;
+
+        println!("{text}");
+
+    } else if disks.is_empty() {
+        println!("No storage devices found in base");
+    }
+
+    if !failed.is_empty() {
+        std::process::exit(1);
+    }
+
+    Ok(())
+}
+
+
+/// Unmount all filesystems of the disk and power it off.
+fn release(disk: &Disk, udisks: bool) -> Result<()> {
+    let holders = disk.holders();
+    if !holders.is_empty() {
+        anyhow::bail!("In use by {}", holders.join(", "));
+    }
+
+    let mounts = disk.mounts()
+        .context("Failed to read mount table")?;
+
+    for (node, mount) in mounts {
+        sys::storage::unmount(&node, &mount, udisks)
+            .with_context(|| format!("Failed to unmount {}", mount.mountpoint.display()))?;
+    }
+
+    disk.power_off(udisks)
+        .context("Failed to power off drive")?;
+
+    Ok(())
+}
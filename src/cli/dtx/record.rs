@@ -0,0 +1,382 @@
+use std::convert::{TryFrom, TryInto};
+use std::fs::File;
+use std::io::{BufRead, BufReader, BufWriter, Write};
+use std::path::Path;
+use std::time::{Duration, SystemTime};
+
+use crate::cli::dtx::PrettyEvent;
+use crate::cli::dtx::timestamp::{self, Timestamp};
+
+use anyhow::{Context, Result};
+use serde_json::Value;
+
+
+/// Mask of the category bits in error codes.
+const CATEGORY_MASK: u16 = 0xf000;
+
+
+/// Writer for event recordings.
+///
+/// Recordings are in JSON lines format, one event per line as printed by
+/// `monitor --quiet`, with the time in seconds since the start of the
+/// recording. Unknown events keep their raw code and payload.
+pub struct Recorder {
+    file: BufWriter<File>,
+}
+
+impl Recorder {
+    pub fn create(path: &Path) -> Result<Self> {
+        let file = File::create(path)
+            .with_context(|| format!("Failed to create recording {path:?}"))?;
+
+        Ok(Recorder { file: BufWriter::new(file) })
+    }
+
+    pub fn write(&mut self, event: sdtx::Event, offset: Duration) -> Result<sdtx::Event> {
+        let event = PrettyEvent { event, time: Some(Timestamp::Monotonic(offset)), model: None };
+
+        serde_json::to_writer(&mut self.file, &event)
+            .context("Failed to serialize data")?;
+
+        writeln!(self.file)
+            .and_then(|_| self.file.flush())
+            .context("Failed to write recording")?;
+
+        Ok(event.event)
+    }
+}
+
+
+/// Load a recording, returning its events with their time since the start
+/// of the recording.
+///
+/// Besides recordings, this accepts the output of `monitor --quiet` and the
+/// persistent event log with RFC 3339 timestamps, which are then taken
+/// relative to the first event.
+pub fn load(path: &Path) -> Result<Vec<(Duration, sdtx::Event)>> {
+    let mut start = None;
+
+    let events = read(path)?
+        .into_iter()
+        .map(|(time, event)| {
+            let time = match time {
+                Time::None => Duration::ZERO,
+                Time::Offset(t) => t,
+                Time::Wall(t) => {
+                    // Wall-clock times are taken relative to the first event.
+                    let start = *start.get_or_insert(t);
+                    t.duration_since(start).unwrap_or_default()
+                },
+            };
+
+            (time, event)
+        })
+        .collect();
+
+    Ok(events)
+}
+
+/// Load a log with RFC 3339 timestamps, e.g. the persistent event log,
+/// returning its events with their wall-clock time.
+pub fn load_log(path: &Path) -> Result<Vec<(SystemTime, sdtx::Event)>> {
+    read(path)?
+        .into_iter()
+        .map(|(time, event)| match time {
+            Time::Wall(t) => Ok((t, event)),
+            _ => anyhow::bail!("Expected RFC 3339 times in {path:?}, not a recording"),
+        })
+        .collect()
+}
+
+
+/// Time of an event as stored in a recording.
+enum Time {
+    None,
+    Offset(Duration),
+    Wall(SystemTime),
+}
+
+fn read(path: &Path) -> Result<Vec<(Time, sdtx::Event)>> {
+    let file = File::open(path)
+        .with_context(|| format!("Failed to open recording {path:?}"))?;
+
+    let mut events = Vec::new();
+
+    for (n, line) in BufReader::new(file).lines().enumerate() {
+        let line = line.context("Failed to read recording")?;
+        if line.trim().is_empty() {
+            continue;
+        }
+
+        let value: Value = serde_json::from_str(&line)
+            .with_context(|| format!("Invalid JSON on line {}", n + 1))?;
+
+        let time = match value.get("time") {
+            None => Time::None,
+            Some(Value::Number(t)) => {
+                let t = t.as_f64()
+                    .and_then(|t| Duration::try_from_secs_f64(t).ok())
+                    .with_context(|| format!("Invalid time on line {}", n + 1))?;
+
+                Time::Offset(t)
+            },
+            Some(Value::String(t)) => {
+                let t = timestamp::parse_rfc3339(t)
+                    .with_context(|| format!("Invalid time on line {}", n + 1))?;
+
+                Time::Wall(t)
+            },
+            Some(_) => anyhow::bail!("Invalid time on line {}", n + 1),
+        };
+
+        let event = parse_event(&value)
+            .with_context(|| format!("Invalid event on line {}", n + 1))?;
+
+        events.push((time, event));
+    }
+
+    Ok(events)
+}
+
+
+/// Parse an event from its JSON representation as produced by
+/// [`PrettyEvent`].
+fn parse_event(value: &Value) -> Option<sdtx::Event> {
+    use sdtx::Event;
+
+    let ty = value.get("type")?;
+
+    let event = match ty.as_str() {
+        Some("request") => Event::Request,
+
+        Some("cancel") => Event::Cancel {
+            reason: parse_cancel_reason(value.get("reason")?)?,
+        },
+
+        Some("base-connection") => Event::BaseConnection {
+            state: parse_base_state(value.get("state")?)?,
+            device_type: parse_device_type(value.get("device-type")?)?,
+            id: num(value.get("id")?)?,
+        },
+
+        Some("latch-status") => Event::LatchStatus {
+            status: parse_latch_status(value.get("status")?)?,
+        },
+
+        Some("device-mode") => Event::DeviceMode {
+            mode: parse_device_mode(value.get("mode")?)?,
+        },
+
+        Some(_) => return None,
+
+        None => {
+            let data = value.get("data")?
+                .as_array()?
+                .iter()
+                .map(num)
+                .collect::<Option<Vec<u8>>>()?;
+
+            Event::Unknown { code: num(ty)?, data }
+        },
+    };
+
+    Some(event)
+}
+
+fn parse_hardware_error(value: &Value) -> Option<sdtx::HardwareError> {
+    use sdtx::{HardwareError, uapi};
+
+    let error = match value.as_str() {
+        Some("failed-to-open")        => HardwareError::FailedToOpen,
+        Some("failed-to-remain-open") => HardwareError::FailedToRemainOpen,
+        Some("failed-to-close")       => HardwareError::FailedToClose,
+        Some(_)                       => return None,
+        None => {
+            let code: u16 = num(value)?;
+            if code & CATEGORY_MASK != uapi::SDTX_CATEGORY_HARDWARE_ERROR {
+                return None;
+            }
+
+            HardwareError::Unknown((code & !CATEGORY_MASK).try_into().ok()?)
+        },
+    };
+
+    Some(error)
+}
+
+fn parse_cancel_reason(value: &Value) -> Option<sdtx::event::CancelReason> {
+    use sdtx::{RuntimeError, uapi};
+    use sdtx::event::CancelReason;
+
+    if let Some(error) = parse_hardware_error(value) {
+        return Some(CancelReason::Hardware(error));
+    }
+
+    let reason = match value.as_str() {
+        Some("not-feasible") => CancelReason::Runtime(RuntimeError::NotFeasible),
+        Some("timeout")      => CancelReason::Runtime(RuntimeError::Timeout),
+        Some(_)              => return None,
+        None => {
+            let code: u16 = num(value)?;
+
+            if code & CATEGORY_MASK == uapi::SDTX_CATEGORY_RUNTIME_ERROR {
+                let code = (code & !CATEGORY_MASK).try_into().ok()?;
+                CancelReason::Runtime(RuntimeError::Unknown(code))
+            } else {
+                CancelReason::Unknown(num(value)?)
+            }
+        },
+    };
+
+    Some(reason)
+}
+
+fn parse_base_state(value: &Value) -> Option<sdtx::event::BaseState> {
+    use sdtx::event::BaseState;
+
+    let state = match value.as_str() {
+        Some("attached")     => BaseState::Attached,
+        Some("detached")     => BaseState::Detached,
+        Some("not-feasible") => BaseState::NotFeasible,
+        Some(_)              => return None,
+        None                 => BaseState::Unknown(num(value)?),
+    };
+
+    Some(state)
+}
+
+fn parse_device_type(value: &Value) -> Option<sdtx::DeviceType> {
+    use sdtx::DeviceType;
+
+    let ty = match value.as_str() {
+        Some("hid") => DeviceType::Hid,
+        Some("ssh") => DeviceType::Ssh,
+        Some(_)     => return None,
+        None        => DeviceType::Unknown(num(value)?),
+    };
+
+    Some(ty)
+}
+
+fn parse_latch_status(value: &Value) -> Option<sdtx::event::LatchStatus> {
+    use sdtx::event::LatchStatus;
+
+    if let Some(error) = parse_hardware_error(value) {
+        return Some(LatchStatus::Error(error));
+    }
+
+    let status = match value.as_str() {
+        Some("closed") => LatchStatus::Closed,
+        Some("opened") => LatchStatus::Opened,
+        Some(_)        => return None,
+        None           => LatchStatus::Unknown(num(value)?),
+    };
+
+    Some(status)
+}
+
+fn parse_device_mode(value: &Value) -> Option<sdtx::event::DeviceMode> {
+    use sdtx::event::DeviceMode;
+
+    let mode = match value.as_str() {
+        Some("tablet") => DeviceMode::Tablet,
+        Some("laptop") => DeviceMode::Laptop,
+        Some("studio") => DeviceMode::Studio,
+        Some(_)        => return None,
+        None           => DeviceMode::Unknown(num(value)?),
+    };
+
+    Some(mode)
+}
+
+/// Parse an unsigned number, converting it to the target type.
+fn num<T: TryFrom<u64>>(value: &Value) -> Option<T> {
+    value.as_u64()?.try_into().ok()
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use sdtx::event::{BaseState, CancelReason, DeviceMode, LatchStatus};
+    use sdtx::{DeviceType, Event, HardwareError, RuntimeError};
+
+    fn temp_path(name: &str) -> std::path::PathBuf {
+        std::env::temp_dir().join(format!("surface-test-{name}-{}", std::process::id()))
+    }
+
+    #[test]
+    fn round_trip() {
+        let path = temp_path("round-trip");
+
+        let events = vec![
+            (Duration::from_millis(0), Event::Request),
+            (Duration::from_millis(250), Event::Cancel { reason: CancelReason::Runtime(RuntimeError::Timeout) }),
+            (Duration::from_millis(500), Event::Cancel { reason: CancelReason::Hardware(HardwareError::FailedToOpen) }),
+            (Duration::from_millis(750), Event::LatchStatus { status: LatchStatus::Opened }),
+            (Duration::from_millis(1000), Event::BaseConnection {
+                state: BaseState::Detached,
+                device_type: DeviceType::Ssh,
+                id: 0x0e,
+            }),
+            (Duration::from_millis(1250), Event::DeviceMode { mode: DeviceMode::Tablet }),
+            (Duration::from_millis(1500), Event::LatchStatus { status: LatchStatus::Unknown(0x42) }),
+            (Duration::from_millis(1750), Event::Unknown { code: 0x1234, data: vec![1, 2, 3] }),
+        ];
+
+        let mut recorder = Recorder::create(&path).unwrap();
+        for (time, event) in &events {
+            recorder.write(event.clone(), *time).unwrap();
+        }
+        drop(recorder);
+
+        let loaded = load(&path).unwrap();
+        std::fs::remove_file(&path).unwrap();
+
+        assert_eq!(loaded, events);
+    }
+
+    #[test]
+    fn rfc3339() {
+        let path = temp_path("rfc3339");
+
+        std::fs::write(&path, concat!(
+            r#"{"type":"request","time":"2024-02-29T23:59:59.500000Z"}"#, "\n",
+            r#"{"type":"latch-status","status":"opened","time":"2024-03-01T00:00:01.000000Z"}"#, "\n",
+            r#"{"type":"device-mode","mode":"tablet"}"#, "\n",
+        )).unwrap();
+
+        let loaded = load(&path).unwrap();
+        std::fs::remove_file(&path).unwrap();
+
+        assert_eq!(loaded, vec![
+            (Duration::ZERO, Event::Request),
+            (Duration::from_millis(1500), Event::LatchStatus { status: LatchStatus::Opened }),
+            (Duration::ZERO, Event::DeviceMode { mode: DeviceMode::Tablet }),
+        ]);
+    }
+
+    #[test]
+    fn invalid_time() {
+        let path = temp_path("invalid-time");
+
+        std::fs::write(&path, r#"{"type":"request","time":"yesterday"}"#).unwrap();
+
+        let err = load(&path).unwrap_err();
+        std::fs::remove_file(&path).unwrap();
+
+        assert_eq!(err.to_string(), "Invalid time on line 1");
+    }
+
+    #[test]
+    fn log_needs_wall_clock_time() {
+        let path = temp_path("log-time");
+
+        std::fs::write(&path, r#"{"type":"request","time":1.5}"#).unwrap();
+
+        let err = load_log(&path).unwrap_err();
+        std::fs::remove_file(&path).unwrap();
+
+        assert!(err.to_string().contains("Expected RFC 3339 times"), "{:#}", err);
+    }
+}
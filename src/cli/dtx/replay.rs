@@ -0,0 +1,84 @@
+use std::path::PathBuf;
+use std::time::{Duration, Instant};
+
+use crate::cli::dtx::{hooks, load_models, monitor, notify, record, PrettyEvent};
+use crate::cli::dtx::timestamp::Timestamp;
+
+use anyhow::Result;
+
+
+pub fn command() -> clap::Command {
+    use clap::Arg;
+
+    clap::Command::new("replay")
+        .about("Replay DTX events recorded via 'monitor --record'")
+        .arg(Arg::new("file")
+            .help("The recording to replay")
+            .value_name("FILE")
+            .value_parser(clap::value_parser!(PathBuf))
+            .required(true))
+        .arg(Arg::new("speed")
+            .help("Speed-up factor, or 0 to replay without delays")
+            .long("speed")
+            .value_name("FACTOR")
+            .value_parser(clap::value_parser!(f64))
+            .default_value("1"))
+        .arg(Arg::new("hooks")
+            .help("Also run the hooks in the given directory, see 'hooks'")
+            .long("hooks")
+            .value_name("DIR")
+            .value_parser(clap::value_parser!(PathBuf)))
+        .arg(Arg::new("notify")
+            .help("Also show desktop notifications, see 'notify'. Their actions have no effect")
+            .long("notify")
+            .action(clap::ArgAction::SetTrue))
+        .arg(Arg::new("hook-timeout")
+            .help("Maximum time in seconds a single hook may run before it is killed")
+            .long("hook-timeout")
+            .value_name("SECS")
+            .value_parser(clap::value_parser!(u64))
+            .default_value("30"))
+}
+
+pub fn execute(m: &clap::ArgMatches) -> Result<()> {
+    let quiet = m.get_flag("quiet");
+    let file: &PathBuf = m.get_one("file").unwrap();
+    let speed: f64 = *m.get_one("speed").unwrap();
+    let hook_dir: Option<&PathBuf> = m.get_one("hooks");
+    let hook_timeout = Duration::from_secs(*m.get_one("hook-timeout").unwrap());
+
+    if !(speed >= 0.0 && speed.is_finite()) {
+        anyhow::bail!("Invalid speed factor: {speed}");
+    }
+
+    let models = load_models();
+
+    let events = record::load(file)?;
+    let mut hooks = hook_dir.map(|dir| hooks::Dispatcher::new(dir, hook_timeout, quiet));
+
+    let mut notifier = match m.get_flag("notify") {
+        true  => Some(notify::Notifier::connect()?),
+        false => None,
+    };
+    let start = Instant::now();
+
+    for (time, event) in events {
+        if speed > 0.0 {
+            let due = start + time.div_f64(speed);
+            std::thread::sleep(due.saturating_duration_since(Instant::now()));
+        }
+
+        let event = PrettyEvent::new(event, Some(Timestamp::Monotonic(time)), &models);
+        monitor::print(quiet, &event)?;
+
+        if let Some(notifier) = &mut notifier {
+            notifier.show(&models, &event.event)?;
+        }
+
+        if let Some(hooks) = &mut hooks {
+            hooks.dispatch(&models, event.event)?;
+        }
+    }
+
+    Ok(())
+}
@@ -0,0 +1,99 @@
+use std::path::Path;
+
+use crate::cli::dtx::events::{self, EventReceiver, Received};
+use crate::cli::dtx::handshake::{Tracker, STATE_FILE};
+use crate::cli::dtx::history::{EventLog, LOG_FILE};
+use crate::cli::dtx::timestamp::Timestamp;
+use crate::sys;
+
+use anyhow::{Context, Result};
+
+
+pub fn command() -> clap::Command {
+    use clap::Arg;
+
+    clap::Command::new("state")
+        .about("Show the current phase of the detachment handshake")
+        .arg(Arg::new("follow")
+            .help("Keep tracking the phase and report changes, required for other calls to know the phase")
+            .long("follow")
+            .short('f')
+            .action(clap::ArgAction::SetTrue))
+        .arg(events::reconnect_arg()
+            .requires("follow"))
+}
+
+pub fn execute(m: &clap::ArgMatches) -> Result<()> {
+    let quiet = m.get_flag("quiet");
+
+    if m.get_flag("follow") {
+        let device = sys::dtx::open()
+            .context("Failed to open DTX device")?;
+
+        return follow(&*device, quiet, m.get_flag("reconnect"));
+    }
+
+    match Tracker::load(Path::new(STATE_FILE))? {
+        Some(tracker) => print(quiet, &tracker, true),
+        None => {
+            // Without a running tracker, we can only guess from the latch.
+            let device = sys::dtx::open()
+                .context("Failed to open DTX device")?;
+
+            print(quiet, &Tracker::from_device(&*device)?, false)
+        },
+    }
+}
+
+fn follow(device: &dyn sys::dtx::Backend, quiet: bool, reconnect: bool) -> Result<()> {
+    let path = Path::new(STATE_FILE);
+
+    // Set up the event stream first so that we don't miss any changes.
+    let events = EventReceiver::open(device)?
+        .reconnecting(reconnect);
+
+    let mut log = EventLog::new(LOG_FILE);
+
+    let mut tracker = Tracker::from_device(device)?;
+    tracker.store(path)?;
+    print(quiet, &tracker, true)?;
+
+    loop {
+        let changed = match events.recv_any()? {
+            Received::Event(event) => tracker.update(&log.write(event)?),
+            Received::Reconnected(snapshot) => tracker.resync(snapshot.latch),
+        };
+
+        if changed {
+            tracker.store(path)?;
+            print(quiet, &tracker, true)?;
+        }
+    }
+}
+
+/// Print the phase of the tracker. If `known` is false, the time it has been
+/// entered is not known.
+fn print(quiet: bool, tracker: &Tracker, known: bool) -> Result<()> {
+    let since = Timestamp::Realtime(tracker.since());
+    let elapsed = tracker.elapsed().as_secs_f64();
+
+    if !quiet {
+        if known {
+            println!("{} (since {since}, {elapsed:.1}s ago)", tracker.phase());
+        } else {
+            println!("{} (since unknown, not tracked)", tracker.phase());
+        }
+
+    } else {
+        let text = serde_json::json!({
+            "phase": tracker.phase().name(),
+            "reason": tracker.phase().reason(),
+            "since": known.then_some(since),
+            "elapsed": known.then_some(elapsed),
+        });
+
+        println!("{text}");
+    }
+
+    Ok(())
+}
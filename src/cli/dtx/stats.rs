@@ -0,0 +1,286 @@
+use std::collections::BTreeMap;
+use std::path::PathBuf;
+use std::time::{Duration, SystemTime};
+
+use crate::cli::dtx::{hardware_error_str, runtime_error_str};
+use crate::cli::dtx::history::{rotated_path, LOG_FILE};
+use crate::cli::dtx::record;
+use crate::cli::dtx::timestamp::Timestamp;
+
+use anyhow::{Context, Result};
+
+
+pub fn command() -> clap::Command {
+    use clap::Arg;
+
+    clap::Command::new("stats")
+        .about("Show detachment statistics from the persistent event log")
+        .arg(Arg::new("log")
+            .help("The event log to evaluate")
+            .long("log")
+            .value_name("FILE")
+            .value_parser(clap::value_parser!(PathBuf))
+            .default_value(LOG_FILE))
+}
+
+pub fn execute(m: &clap::ArgMatches) -> Result<()> {
+    let quiet = m.get_flag("quiet");
+    let path: &PathBuf = m.get_one("log").unwrap();
+
+    let mut stats = Stats::default();
+
+    // Include the log from before the last rotation. No log simply means
+    // nothing has been recorded yet.
+    for path in [rotated_path(path), path.clone()] {
+        if !path.exists() {
+            continue;
+        }
+
+        for (time, event) in record::load_log(&path)? {
+            stats.add(time, &event);
+        }
+    }
+
+    if !quiet {
+        print!("{stats}");
+
+    } else {
+        let text = serde_json::to_string(&stats)
+            .context("Failed to serialize data")?;
+
+        println!("{text}");
+    }
+
+    Ok(())
+}
+
+
+#[derive(Default)]
+struct Stats {
+    first: Option<SystemTime>,
+    last: Option<SystemTime>,
+
+    attempts: u64,
+    successes: u64,
+
+    /// Number of cancellations per (category, reason).
+    cancellations: BTreeMap<(&'static str, String), u64>,
+
+    pending: Option<SystemTime>,
+    open_time_total: Duration,
+    open_time_count: u32,
+
+    attached: u64,
+    detached: u64,
+}
+
+impl Stats {
+    /// Add an event with its wall-clock time.
+    fn add(&mut self, time: SystemTime, event: &sdtx::Event) {
+        use sdtx::Event;
+        use sdtx::event::{BaseState, CancelReason, LatchStatus};
+
+        self.first.get_or_insert(time);
+        self.last = Some(time);
+
+        match event {
+            Event::Request => {
+                self.attempts += 1;
+                self.pending = Some(time);
+            },
+            Event::Cancel { reason } => {
+                // The EC rejects requests that are not feasible, e.g. while
+                // the latch is locked, without announcing them first. These
+                // are attempts all the same.
+                if self.pending.is_none() {
+                    self.attempts += 1;
+                }
+
+                let key = match reason {
+                    CancelReason::Hardware(err) => ("hardware-error", hardware_error_str(*err).to_string()),
+                    CancelReason::Runtime(err)  => ("runtime-error", runtime_error_str(*err).to_string()),
+                    CancelReason::Unknown(x)    => ("unknown", format!("{x:#06x}")),
+                };
+
+                *self.cancellations.entry(key).or_default() += 1;
+                self.pending = None;
+            },
+            Event::LatchStatus { status: LatchStatus::Opened } => {
+                if let Some(start) = self.pending.take() {
+                    self.successes += 1;
+                    self.open_time_total += time.duration_since(start).unwrap_or_default();
+                    self.open_time_count += 1;
+                }
+            },
+            // Only count errors ending a pending request. Those reported with
+            // or after a cancel event have already been counted.
+            Event::LatchStatus { status: LatchStatus::Error(err) } if self.pending.is_some() => {
+                let key = ("hardware-error", hardware_error_str(*err).to_string());
+
+                *self.cancellations.entry(key).or_default() += 1;
+                self.pending = None;
+            },
+            Event::BaseConnection { state: BaseState::Attached, .. } => self.attached += 1,
+            Event::BaseConnection { state: BaseState::Detached, .. } => self.detached += 1,
+            _ => (),
+        }
+    }
+
+    fn canceled(&self) -> u64 {
+        self.cancellations.values().sum()
+    }
+
+    fn mean_open_time(&self) -> Option<Duration> {
+        (self.open_time_count > 0).then(|| self.open_time_total / self.open_time_count)
+    }
+}
+
+impl std::fmt::Display for Stats {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        if let (Some(first), Some(last)) = (self.first, self.last) {
+            let first = Timestamp::Realtime(first);
+            let last = Timestamp::Realtime(last);
+            writeln!(f, "Period:             {first} to {last}")?;
+        }
+
+        writeln!(f, "Attempts:           {}", self.attempts)?;
+        writeln!(f, "Successes:          {}", self.successes)?;
+        writeln!(f, "Cancellations:      {}", self.canceled())?;
+
+        for ((category, reason), count) in &self.cancellations {
+            let name = format!("{category}: {reason}");
+            writeln!(f, "  {name:<32} {count}")?;
+        }
+
+        match self.mean_open_time() {
+            Some(t) => writeln!(f, "Mean time to open:  {:.2}s", t.as_secs_f64())?,
+            None    => writeln!(f, "Mean time to open:  n/a")?,
+        }
+
+        writeln!(f, "Base attached:      {}", self.attached)?;
+        writeln!(f, "Base detached:      {}", self.detached)
+    }
+}
+
+impl serde::Serialize for Stats {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: serde::Serializer
+    {
+        use serde::ser::SerializeStruct;
+
+        let mut cancellations: BTreeMap<&str, BTreeMap<&str, u64>> = BTreeMap::new();
+        for ((category, reason), count) in &self.cancellations {
+            cancellations.entry(category).or_default().insert(reason, *count);
+        }
+
+        let first = self.first.map(Timestamp::Realtime);
+        let last = self.last.map(Timestamp::Realtime);
+
+        let mut s = serializer.serialize_struct("Stats", 8)?;
+        s.serialize_field("first-event", &first)?;
+        s.serialize_field("last-event", &last)?;
+        s.serialize_field("attempts", &self.attempts)?;
+        s.serialize_field("successes", &self.successes)?;
+        s.serialize_field("cancellations", &cancellations)?;
+        s.serialize_field("mean-time-to-open", &self.mean_open_time().map(|t| t.as_secs_f64()))?;
+        s.serialize_field("base-attached", &self.attached)?;
+        s.serialize_field("base-detached", &self.detached)?;
+        s.end()
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::UNIX_EPOCH;
+    use sdtx::event::{BaseState, CancelReason, LatchStatus};
+    use sdtx::{DeviceType, Event, HardwareError, RuntimeError};
+
+    fn stats(events: &[(u64, Event)]) -> Stats {
+        let mut stats = Stats::default();
+        for (time, event) in events {
+            stats.add(UNIX_EPOCH + Duration::from_millis(*time), event);
+        }
+        stats
+    }
+
+    fn count(stats: &Stats, category: &'static str, reason: &str) -> u64 {
+        stats.cancellations.get(&(category, reason.to_owned())).copied().unwrap_or_default()
+    }
+
+    #[test]
+    fn success() {
+        let stats = stats(&[
+            (1000, Event::Request),
+            (3000, Event::LatchStatus { status: LatchStatus::Opened }),
+            (4000, Event::BaseConnection { state: BaseState::Detached, device_type: DeviceType::Ssh, id: 0x0e }),
+            (5000, Event::Request),
+            (6000, Event::LatchStatus { status: LatchStatus::Opened }),
+            (9000, Event::BaseConnection { state: BaseState::Attached, device_type: DeviceType::Ssh, id: 0x0e }),
+        ]);
+
+        assert_eq!((stats.attempts, stats.successes, stats.canceled()), (2, 2, 0));
+        assert_eq!(stats.mean_open_time(), Some(Duration::from_millis(1500)));
+        assert_eq!((stats.attached, stats.detached), (1, 1));
+        assert_eq!(stats.first, Some(UNIX_EPOCH + Duration::from_millis(1000)));
+        assert_eq!(stats.last, Some(UNIX_EPOCH + Duration::from_millis(9000)));
+    }
+
+    #[test]
+    fn cancellations() {
+        let stats = stats(&[
+            (1000, Event::Request),
+            (2000, Event::Cancel { reason: CancelReason::Runtime(RuntimeError::Timeout) }),
+            (3000, Event::Request),
+            (4000, Event::Cancel { reason: CancelReason::Runtime(RuntimeError::Unknown(0x05)) }),
+            (5000, Event::Cancel { reason: CancelReason::Unknown(0x4242) }),
+        ]);
+
+        assert_eq!((stats.attempts, stats.successes, stats.canceled()), (3, 0, 3));
+        assert_eq!(count(&stats, "runtime-error", "timeout"), 1);
+        assert_eq!(count(&stats, "runtime-error", "0x2005"), 1);
+        assert_eq!(count(&stats, "unknown", "0x4242"), 1);
+        assert_eq!(stats.mean_open_time(), None);
+    }
+
+    #[test]
+    fn latch_errors() {
+        let error = |err| Event::LatchStatus { status: LatchStatus::Error(err) };
+
+        let stats = stats(&[
+            // Reported along with a cancel event, counted once.
+            (1000, Event::Request),
+            (2000, Event::Cancel { reason: CancelReason::Hardware(HardwareError::FailedToOpen) }),
+            (2001, error(HardwareError::FailedToOpen)),
+
+            // Reported on its own while pending.
+            (3000, Event::Request),
+            (4000, error(HardwareError::FailedToOpen)),
+
+            // Not pending, e.g. after the latch has been opened.
+            (5000, Event::Request),
+            (6000, Event::LatchStatus { status: LatchStatus::Opened }),
+            (7000, error(HardwareError::FailedToRemainOpen)),
+            (8000, error(HardwareError::FailedToClose)),
+        ]);
+
+        assert_eq!((stats.attempts, stats.successes, stats.canceled()), (3, 1, 2));
+        assert_eq!(count(&stats, "hardware-error", "failed-to-open"), 2);
+        assert_eq!(count(&stats, "hardware-error", "failed-to-remain-open"), 0);
+    }
+
+    #[test]
+    fn not_feasible() {
+        let stats = stats(&[
+            // Rejected right away, without a request event.
+            (1000, Event::Cancel { reason: CancelReason::Runtime(RuntimeError::NotFeasible) }),
+            (2000, Event::Request),
+            (3000, Event::LatchStatus { status: LatchStatus::Opened }),
+        ]);
+
+        assert_eq!((stats.attempts, stats.successes, stats.canceled()), (2, 1, 1));
+        assert_eq!(count(&stats, "runtime-error", "not-feasible"), 1);
+    }
+}
@@ -0,0 +1,234 @@
+use std::convert::TryInto;
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+
+
+#[derive(Debug, Clone, Copy)]
+pub enum Format {
+    /// Seconds since a fixed starting point.
+    Monotonic,
+
+    /// Wall-clock time in UTC, formatted as per RFC 3339.
+    Rfc3339,
+}
+
+
+#[derive(Debug, Clone, Copy)]
+pub enum Timestamp {
+    Monotonic(Duration),
+    Realtime(SystemTime),
+}
+
+impl Timestamp {
+    /// Take a timestamp of the current time, with monotonic timestamps
+    /// relative to `start`.
+    pub fn now(format: Format, start: Instant) -> Self {
+        match format {
+            Format::Monotonic => Timestamp::Monotonic(start.elapsed()),
+            Format::Rfc3339   => Timestamp::Realtime(SystemTime::now()),
+        }
+    }
+}
+
+impl std::fmt::Display for Timestamp {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Timestamp::Monotonic(t) => {
+                write!(f, "{:5}.{:06}", t.as_secs(), t.subsec_micros())
+            },
+            Timestamp::Realtime(t) => {
+                let t = t.duration_since(UNIX_EPOCH).unwrap_or_default();
+                let secs = t.as_secs();
+                let (year, month, day) = civil_from_days((secs / 86400) as i64);
+
+                write!(f, "{:04}-{:02}-{:02}T{:02}:{:02}:{:02}.{:06}Z",
+                    year, month, day,
+                    secs % 86400 / 3600, secs % 3600 / 60, secs % 60,
+                    t.subsec_micros())
+            },
+        }
+    }
+}
+
+impl serde::Serialize for Timestamp {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: serde::Serializer
+    {
+        match self {
+            Timestamp::Monotonic(t) => serializer.serialize_f64(t.as_secs_f64()),
+            Timestamp::Realtime(_)  => serializer.collect_str(self),
+        }
+    }
+}
+
+
+/// Parse a wall-clock time in RFC 3339 format, e.g. as printed by `monitor
+/// --timestamps rfc3339`.
+pub fn parse_rfc3339(s: &str) -> Option<SystemTime> {
+    let (date, time) = s.split_once(['T', 't', ' '])?;
+
+    let (year, date) = date.split_once('-')?;
+    let (month, day) = date.split_once('-')?;
+    let (year, month, day): (i64, u32, u32) = (digits(year, 4)?, digits(month, 2)?, digits(day, 2)?);
+
+    if !(1..=12).contains(&month) || !(1..=31).contains(&day) {
+        return None;
+    }
+
+    let (time, offset) = match time.strip_suffix(['Z', 'z']) {
+        Some(time) => (time, 0),
+        None => {
+            let (time, offset) = time.split_at(time.rfind(['+', '-'])?);
+            let (sign, offset) = offset.split_at(1);
+            let (hours, minutes) = offset.split_once(':')?;
+            let offset: i64 = digits::<i64>(hours, 2)? * 3600 + digits::<i64>(minutes, 2)? * 60;
+
+            (time, if sign == "-" { -offset } else { offset })
+        },
+    };
+
+    let (time, frac) = match time.split_once('.') {
+        Some((time, frac)) => (time, Some(frac)),
+        None               => (time, None),
+    };
+
+    let (hour, time) = time.split_once(':')?;
+    let (minute, second) = time.split_once(':')?;
+    let (hour, minute, second): (i64, i64, i64) = (digits(hour, 2)?, digits(minute, 2)?, digits(second, 2)?);
+
+    // Allow leap seconds, which simply roll over into the next minute.
+    if hour > 23 || minute > 59 || second > 60 {
+        return None;
+    }
+
+    let nanos = match frac {
+        Some(frac) => {
+            let frac = &frac[..frac.len().min(9)];
+            digits::<u32>(frac, frac.len())? * 10u32.pow(9 - frac.len() as u32)
+        },
+        None => 0,
+    };
+
+    let secs = days_from_civil(year, month, day) * 86400 + hour * 3600 + minute * 60 + second - offset;
+
+    Some(UNIX_EPOCH + Duration::new(secs.try_into().ok()?, nanos))
+}
+
+/// Parse a number consisting of exactly `len` decimal digits.
+fn digits<T: std::str::FromStr>(s: &str, len: usize) -> Option<T> {
+    if len == 0 || s.len() != len || !s.bytes().all(|b| b.is_ascii_digit()) {
+        return None;
+    }
+
+    s.parse().ok()
+}
+
+
+/// Convert days since the Unix epoch to a (year, month, day) date.
+///
+/// See <https://howardhinnant.github.io/date_algorithms.html#civil_from_days>.
+fn civil_from_days(days: i64) -> (i64, u32, u32) {
+    let z = days + 719468;
+    let era = z.div_euclid(146097);
+    let doe = z.rem_euclid(146097);
+    let yoe = (doe - doe / 1460 + doe / 36524 - doe / 146096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = (doy - (153 * mp + 2) / 5 + 1) as u32;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 } as u32;
+    let year = yoe + era * 400 + if month <= 2 { 1 } else { 0 };
+
+    (year, month, day)
+}
+
+/// Convert a (year, month, day) date to days since the Unix epoch. Inverse
+/// of [`civil_from_days`].
+///
+/// See <https://howardhinnant.github.io/date_algorithms.html#days_from_civil>.
+fn days_from_civil(year: i64, month: u32, day: u32) -> i64 {
+    let year = if month <= 2 { year - 1 } else { year };
+    let era = year.div_euclid(400);
+    let yoe = year.rem_euclid(400);
+    let mp = (month as i64 + 9) % 12;
+    let doy = (153 * mp + 2) / 5 + day as i64 - 1;
+    let doe = yoe * 365 + yoe / 4 - yoe / 100 + doy;
+
+    era * 146097 + doe - 719468
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn epoch() {
+        assert_eq!(civil_from_days(0), (1970, 1, 1));
+        assert_eq!(civil_from_days(-1), (1969, 12, 31));
+    }
+
+    #[test]
+    fn leap_days() {
+        assert_eq!(civil_from_days(11016), (2000, 2, 29));
+        assert_eq!(civil_from_days(11017), (2000, 3, 1));
+        assert_eq!(civil_from_days(19782), (2024, 2, 29));
+
+        // 2100 is not a leap year.
+        assert_eq!(civil_from_days(47540), (2100, 2, 28));
+        assert_eq!(civil_from_days(47541), (2100, 3, 1));
+    }
+
+    #[test]
+    fn year_boundaries() {
+        assert_eq!(civil_from_days(10956), (1999, 12, 31));
+        assert_eq!(civil_from_days(10957), (2000, 1, 1));
+        assert_eq!(civil_from_days(19722), (2023, 12, 31));
+        assert_eq!(civil_from_days(19723), (2024, 1, 1));
+    }
+
+    #[test]
+    fn format_realtime() {
+        let t = UNIX_EPOCH + Duration::new(1709251199, 123_456_000);
+
+        assert_eq!(Timestamp::Realtime(t).to_string(), "2024-02-29T23:59:59.123456Z");
+        assert_eq!(Timestamp::Realtime(UNIX_EPOCH).to_string(), "1970-01-01T00:00:00.000000Z");
+    }
+
+    #[test]
+    fn civil_round_trip() {
+        for days in (-800_000..800_000).step_by(7) {
+            let (year, month, day) = civil_from_days(days);
+            assert_eq!(days_from_civil(year, month, day), days);
+        }
+    }
+
+    #[test]
+    fn parse() {
+        let t = UNIX_EPOCH + Duration::new(1709251199, 123_456_000);
+
+        assert_eq!(parse_rfc3339("2024-02-29T23:59:59.123456Z"), Some(t));
+        assert_eq!(parse_rfc3339("2024-03-01T01:29:59.123456+01:30"), Some(t));
+        assert_eq!(parse_rfc3339("2024-02-29T20:59:59.123456-03:00"), Some(t));
+        assert_eq!(parse_rfc3339("1970-01-01T00:00:00Z"), Some(UNIX_EPOCH));
+    }
+
+    #[test]
+    fn parse_round_trip() {
+        let t = UNIX_EPOCH + Duration::new(1_800_000_000, 42_000);
+        let s = Timestamp::Realtime(t).to_string();
+
+        assert_eq!(parse_rfc3339(&s), Some(t));
+    }
+
+    #[test]
+    fn parse_invalid() {
+        assert_eq!(parse_rfc3339("2024-02-29"), None);
+        assert_eq!(parse_rfc3339("2024-02-29T23:59:59"), None);
+        assert_eq!(parse_rfc3339("2024-13-01T00:00:00Z"), None);
+        assert_eq!(parse_rfc3339("2024-02-29T24:00:00Z"), None);
+        assert_eq!(parse_rfc3339("2024-02-29T23:59:59.Z"), None);
+        assert_eq!(parse_rfc3339("24-02-29T23:59:59Z"), None);
+        assert_eq!(parse_rfc3339("1969-12-31T23:59:59Z"), None);
+        assert_eq!(parse_rfc3339("12.5"), None);
+    }
+}
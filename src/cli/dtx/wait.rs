@@ -0,0 +1,223 @@
+use std::time::{Duration, Instant};
+
+use crate::cli::dtx::{base_state_str, device_mode_str, latch_status_str};
+use crate::cli::dtx::{event_base_state, event_device_mode, event_latch_status};
+use crate::cli::dtx::events::{self, EventReceiver, Received};
+use crate::sys;
+
+use anyhow::{Context, Result};
+
+
+/// Exit code used when the condition did not hold before the timeout.
+const EXIT_TIMEOUT: i32 = 2;
+
+
+pub fn command() -> clap::Command {
+    use clap::{Arg, ArgGroup};
+
+    clap::Command::new("wait")
+        .about("Wait until the device reaches the given state")
+        .arg(Arg::new("base")
+            .help("Wait for the base to be attached or detached")
+            .long("base")
+            .value_name("STATE")
+            .value_parser(["attached", "detached"]))
+        .arg(Arg::new("mode")
+            .help("Wait for the given device mode")
+            .long("mode")
+            .value_name("MODE")
+            .value_parser(["tablet", "laptop", "studio"]))
+        .arg(Arg::new("latch")
+            .help("Wait for the latch to be closed or opened")
+            .long("latch")
+            .value_name("STATUS")
+            .value_parser(["closed", "opened"]))
+        .arg(Arg::new("timeout")
+            .help("Maximum time in seconds to wait, exits with code 2 if exceeded")
+            .long("timeout")
+            .value_name("SECS")
+            .value_parser(clap::value_parser!(u64)))
+        .arg(events::reconnect_arg())
+        .group(ArgGroup::new("condition")
+            .args(["base", "mode", "latch"])
+            .multiple(true)
+            .required(true))
+}
+
+pub fn execute(m: &clap::ArgMatches) -> Result<()> {
+    let device = sys::dtx::open()
+        .context("Failed to open DTX device")?;
+
+    run(&*device, m)
+}
+
+pub fn run(device: &dyn sys::dtx::Backend, m: &clap::ArgMatches) -> Result<()> {
+    use sdtx::Event;
+
+    let quiet = m.get_flag("quiet");
+    let timeout: Option<u64> = m.get_one("timeout").copied();
+
+    let want = State {
+        base: m.get_one::<String>("base").map(|s| parse(BASE_STATES, base_state_str, s)),
+        mode: m.get_one::<String>("mode").map(|s| parse(DEVICE_MODES, device_mode_str, s)),
+        latch: m.get_one::<String>("latch").map(|s| parse(LATCH_STATES, latch_status_str, s)),
+    };
+
+    // Set up the event stream before querying the current state so that we
+    // don't miss any changes in between.
+    let events = EventReceiver::open(device)?
+        .reconnecting(m.get_flag("reconnect"));
+
+    let mut state = State::default();
+
+    if want.base.is_some() {
+        let info = device.get_base_info()
+            .context("Failed to get base info")?;
+
+        state.base = Some(info.state);
+    }
+
+    if want.mode.is_some() {
+        let mode = device.get_device_mode()
+            .context("Failed to get device mode")?;
+
+        state.mode = Some(mode);
+    }
+
+    if want.latch.is_some() {
+        let status = device.get_latch_status()
+            .context("Failed to get latch status")?;
+
+        state.latch = Some(status);
+    }
+
+    let deadline = timeout.map(|t| Instant::now() + Duration::from_secs(t));
+
+    while !state.satisfies(&want) {
+        let received = match deadline {
+            Some(deadline) => events.recv_any_deadline(deadline)?,
+            None           => Some(events.recv_any()?),
+        };
+
+        match received {
+            Some(Received::Event(event)) => match event {
+                Event::BaseConnection { state: base, .. } => state.base = event_base_state(base),
+                Event::DeviceMode { mode }                => state.mode = event_device_mode(mode),
+                Event::LatchStatus { status }             => state.latch = event_latch_status(status),
+                _ => (),
+            },
+            Some(Received::Reconnected(snapshot)) => {
+                // We may have missed events, start over from the snapshot.
+                state = State {
+                    base: Some(snapshot.base.state),
+                    mode: Some(snapshot.mode),
+                    latch: Some(snapshot.latch),
+                };
+            },
+            None => {
+                if !quiet {
+                    println!("Timed out");
+                }
+                std::process::exit(EXIT_TIMEOUT);
+            },
+        }
+    }
+
+    if !quiet {
+        println!("Condition met");
+    }
+
+    Ok(())
+}
+
+
+const BASE_STATES: &[sdtx::BaseState] = &[sdtx::BaseState::Attached, sdtx::BaseState::Detached];
+
+const DEVICE_MODES: &[sdtx::DeviceMode] = &[
+    sdtx::DeviceMode::Tablet,
+    sdtx::DeviceMode::Laptop,
+    sdtx::DeviceMode::Studio,
+];
+
+const LATCH_STATES: &[sdtx::LatchStatus] = &[sdtx::LatchStatus::Closed, sdtx::LatchStatus::Opened];
+
+/// Look up the value with the given name. The name has already been
+/// validated by clap.
+fn parse<T, S>(values: &[T], name: fn(T) -> S, arg: &str) -> T
+where
+    T: Copy,
+    S: AsRef<str>,
+{
+    values.iter()
+        .copied()
+        .find(|v| name(*v).as_ref() == arg)
+        .unwrap()
+}
+
+
+#[derive(Default)]
+struct State {
+    base: Option<sdtx::BaseState>,
+    mode: Option<sdtx::DeviceMode>,
+    latch: Option<sdtx::LatchStatus>,
+}
+
+impl State {
+    /// Check whether all conditions set in `want` are met.
+    fn satisfies(&self, want: &State) -> bool {
+        fn check<T: PartialEq>(want: &Option<T>, have: &Option<T>) -> bool {
+            want.is_none() || want == have
+        }
+
+        check(&want.base, &self.base)
+            && check(&want.mode, &self.mode)
+            && check(&want.latch, &self.latch)
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cli::dtx::matches;
+    use crate::sys::dtx::Mock;
+
+    #[test]
+    fn already_met() {
+        let mock = Mock::new();
+
+        run(&mock, &matches(&["wait", "-q", "--base", "attached", "--latch", "closed"])).unwrap();
+    }
+
+    #[test]
+    fn wait_for_events() {
+        let mock = Mock::new();
+
+        let user = mock.clone();
+        std::thread::spawn(move || {
+            std::thread::sleep(Duration::from_millis(100));
+            user.press();
+            std::thread::sleep(Duration::from_millis(100));
+            user.detach();
+        });
+
+        let m = matches(&["wait", "-q", "--base", "detached", "--mode", "tablet", "--timeout", "2"]);
+        run(&mock, &m).unwrap();
+    }
+
+    #[test]
+    fn reconnect() {
+        let mock = Mock::new();
+
+        // The base goes away while the device is gone, so its event is lost.
+        let user = mock.clone();
+        std::thread::spawn(move || {
+            std::thread::sleep(Duration::from_millis(100));
+            user.unplug(Duration::from_millis(300));
+            user.detach();
+        });
+
+        let m = matches(&["wait", "-q", "--base", "detached", "--timeout", "3", "--reconnect"]);
+        run(&mock, &m).unwrap();
+    }
+}
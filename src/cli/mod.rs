@@ -3,9 +3,19 @@ pub mod dtx;
 pub mod profile;
 pub mod status;
 
-use anyhow::Result;
+use anyhow::{Context, Result};
 
 use std::collections::HashMap;
+use std::process::{Child, ExitStatus};
+use std::time::Duration;
+
+use nix::sys::signal::Signal;
+use nix::unistd::Pid;
+
+use crate::sys;
+
+
+const CHILD_POLL_INTERVAL: Duration = Duration::from_millis(100);
 
 
 pub trait Command {
@@ -66,3 +76,36 @@ impl Registry {
 pub fn build() -> Registry {
     Registry::build()
 }
+
+
+/// Exit code to pass on the exit status of a child process, following the
+/// shell convention of `128 + signal` for processes killed by a signal.
+pub fn exit_code(status: ExitStatus) -> i32 {
+    use std::os::unix::process::ExitStatusExt;
+
+    status.code()
+        .or_else(|| status.signal().map(|sig| 128 + sig))
+        .unwrap_or(1)
+}
+
+/// Wait for the child to exit, forwarding termination signals caught via
+/// [`sys::signal::catch_termination`] to it.
+pub fn wait_child(child: &mut Child) -> Result<ExitStatus> {
+    let pid = Pid::from_raw(child.id() as i32);
+
+    loop {
+        if let Some(status) = child.try_wait().context("Failed to wait for child")? {
+            return Ok(status);
+        }
+
+        // SIGINT from the terminal already reaches the whole process group.
+        match sys::signal::take() {
+            Some(Signal::SIGINT) | None => (),
+            Some(signal) => {
+                let _ = nix::sys::signal::kill(pid, signal);
+            },
+        }
+
+        std::thread::sleep(CHILD_POLL_INTERVAL);
+    }
+}
@@ -3,6 +3,8 @@ use crate::sys;
 
 use anyhow::Result;
 
+use std::path::{Path, PathBuf};
+
 
 pub struct Command;
 
@@ -12,12 +14,20 @@ impl DynCommand for Command {
     }
 
     fn build(&self) -> clap::Command {
+        use clap::Arg;
+
         clap::Command::new(self.name())
             .about("Show an overview of the current system status")
+            .arg(Arg::new("pci-ids")
+                .help("PCI ID database used to resolve names")
+                .long("pci-ids")
+                .value_name("FILE")
+                .value_parser(clap::value_parser!(PathBuf)))
     }
 
-    fn execute(&self, _: &clap::ArgMatches) -> Result<()> {
-        let stats = Stats::load();
+    fn execute(&self, m: &clap::ArgMatches) -> Result<()> {
+        let pci_ids: Option<&PathBuf> = m.get_one("pci-ids");
+        let stats = Stats::load(pci_ids.map(|p| p.as_path()));
 
         if stats.available() {
             print!("{stats}");
@@ -43,8 +53,11 @@ struct ProfileStats {
 struct DgpuStats {
     vendor:      Option<u16>,
     device:      Option<u16>,
+    subsystem:   Option<(u16, u16)>,
+    names:       sys::pciids::Names,
     power_state: Option<sys::pci::PowerState>,
     runtime_pm:  Option<sys::pci::RuntimePowerManagement>,
+    nvidia:      Option<sys::nvidia::PowerReport>,
 }
 
 struct DtxStats {
@@ -52,14 +65,15 @@ struct DtxStats {
     base_state:   Option<sdtx::BaseState>,
     base_type:    Option<sdtx::DeviceType>,
     base_id:      Option<u8>,
+    base_model:   Option<String>,
     latch_status: Option<sdtx::LatchStatus>,
 }
 
 
 impl Stats {
-    fn load() -> Self {
+    fn load(pci_ids: Option<&Path>) -> Self {
         let prof = ProfileStats::load();
-        let dgpu = DgpuStats::load();
+        let dgpu = DgpuStats::load(pci_ids);
         let dtx  = DtxStats::load();
 
         Stats { prof, dgpu, dtx }
@@ -91,15 +105,20 @@ impl ProfileStats {
 }
 
 impl DgpuStats {
-    fn load() -> Self {
+    fn load(pci_ids: Option<&Path>) -> Self {
         let dev = crate::cli::dgpu::find_dgpu_device().ok().and_then(|x| x);
 
         let vendor = dev.as_ref().and_then(|d| d.vendor_id().ok());
         let device = dev.as_ref().and_then(|d| d.device_id().ok());
+        let subsystem = dev.as_ref().and_then(|d| d.subsystem_vendor_id().ok().zip(d.subsystem_device_id().ok()));
+        let names = dev.as_ref()
+            .and_then(|d| crate::cli::dgpu::lookup_dgpu_names(d, pci_ids).ok())
+            .unwrap_or_default();
         let power_state = dev.as_ref().and_then(|d| d.get_power_state().ok());
         let runtime_pm = dev.as_ref().and_then(|d| d.get_runtime_pm().ok());
+        let nvidia = dev.as_ref().and_then(|d| sys::nvidia::PowerReport::load(d).ok().flatten());
 
-        DgpuStats { vendor, device, power_state, runtime_pm }
+        DgpuStats { vendor, device, subsystem, names, power_state, runtime_pm, nvidia }
     }
 
     fn available(&self) -> bool {
@@ -112,13 +131,16 @@ impl DgpuStats {
 
 impl DtxStats {
     fn load() -> Self {
-        let dev = sdtx::Device::open().ok();
+        let dev = sys::dtx::open().ok();
 
         let base = dev.as_ref().and_then(|d| d.get_base_info().ok());
         let base_state = base.map(|b| b.state);
         let base_type = base.map(|b| b.device_type);
         let base_id = base.map(|b| b.id);
 
+        let models = crate::cli::dtx::load_models();
+        let base_model = base.map(|b| models.name(b.device_type, b.id));
+
         let device_mode = dev.as_ref().and_then(|d| d.get_device_mode().ok());
         let latch_status = dev.as_ref().and_then(|d| d.get_latch_status().ok());
 
@@ -127,6 +149,7 @@ impl DtxStats {
             base_state,
             base_type,
             base_id,
+            base_model,
             latch_status,
         }
     }
@@ -181,10 +204,28 @@ impl std::fmt::Display for DgpuStats {
         writeln!(f, "Discrete GPU:")?;
 
         if let Some(vendor) = self.vendor {
-            writeln!(f, "  Vendor:         {vendor:04x}")?;
+            write!(f, "  Vendor:         {vendor:04x}")?;
+            if let Some(name) = &self.names.vendor {
+                write!(f, " ({name})")?;
+            }
+            writeln!(f)?;
         }
         if let Some(device) = self.device {
-            writeln!(f, "  Device:         {device:04x}")?;
+            write!(f, "  Device:         {device:04x}")?;
+            if let Some(name) = &self.names.device {
+                write!(f, " ({name})")?;
+            }
+            writeln!(f)?;
+        }
+        if let Some((sv, sd)) = self.subsystem {
+            write!(f, "  Subsystem:      {sv:04x}:{sd:04x}")?;
+            match (&self.names.subsystem_vendor, &self.names.subsystem) {
+                (Some(v), Some(d)) => write!(f, " ({v} {d})")?,
+                (Some(v), None)    => write!(f, " ({v})")?,
+                (None, Some(d))    => write!(f, " ({d})")?,
+                (None, None)       => (),
+            }
+            writeln!(f)?;
         }
         if let Some(power_state) = self.power_state {
             writeln!(f, "  Power State:    {power_state}")?;
@@ -192,6 +233,14 @@ impl std::fmt::Display for DgpuStats {
         if let Some(runtime_pm) = self.runtime_pm {
             writeln!(f, "  Runtime PM:     {runtime_pm}")?;
         }
+        if let Some(nvidia) = &self.nvidia {
+            if let Some(status) = &nvidia.runtime_d3_status {
+                writeln!(f, "  Runtime D3:     {status}")?;
+            }
+            if let Some(vmem) = &nvidia.video_memory {
+                writeln!(f, "  Video Memory:   {vmem}")?;
+            }
+        }
 
         writeln!(f)
     }
@@ -217,6 +266,9 @@ impl std::fmt::Display for DtxStats {
         if let Some(base_id) = self.base_id {
             writeln!(f, "  Base ID:        {base_id:#04x}")?;
         }
+        if let Some(base_model) = &self.base_model {
+            writeln!(f, "  Base Model:     {base_model}")?;
+        }
         if let Some(latch_status) = self.latch_status {
             writeln!(f, "  Latch Status:   {latch_status}")?;
         }
@@ -0,0 +1,157 @@
+use std::path::PathBuf;
+
+use crate::sys::{Error, Result};
+
+
+/// USB device class of hubs.
+const USB_CLASS_HUB: &str = "09";
+
+
+/// Devices located in the detachable base of a Surface Book.
+///
+/// The base is described by a set of root devices (e.g. the USB hub behind
+/// the base connector and the dGPU), with everything below them considered
+/// to be part of the base.
+pub struct Base {
+    roots: Vec<udev::Device>,
+}
+
+impl Base {
+    pub fn new(roots: Vec<udev::Device>) -> Self {
+        Base { roots }
+    }
+
+    /// All devices in the base, optionally limited to the given subsystem.
+    pub fn devices(&self, subsystem: Option<&str>) -> Result<Vec<udev::Device>> {
+        let mut devices: Vec<udev::Device> = Vec::new();
+
+        for root in &self.roots {
+            let mut enumerator = udev::Enumerator::new()
+                .map_err(|source| Error::Io { source })?;
+
+            if let Some(subsystem) = subsystem {
+                enumerator.match_subsystem(subsystem)
+                    .map_err(|source| Error::Io { source })?;
+            }
+
+            enumerator.match_parent(root)
+                .map_err(|source| Error::Io { source })?;
+
+            let found = enumerator.scan_devices()
+                .map_err(|source| Error::Io { source })?;
+
+            for device in found {
+                if !devices.iter().any(|d| d.syspath() == device.syspath()) {
+                    devices.push(device);
+                }
+            }
+        }
+
+        Ok(devices)
+    }
+
+    /// Device nodes of all devices in the base.
+    pub fn device_nodes(&self) -> Result<Vec<PathBuf>> {
+        let nodes = self.devices(None)?
+            .into_iter()
+            .filter_map(|d| d.devnode().map(|n| n.to_owned()))
+            .collect();
+
+        Ok(nodes)
+    }
+
+    /// ALSA cards in the base with a PCM stream that is currently open.
+    pub fn active_audio_streams(&self) -> Result<Vec<String>> {
+        let mut active = Vec::new();
+
+        for card in self.devices(Some("sound"))? {
+            let sysname = card.sysname().to_string_lossy();
+            let index = match sysname.strip_prefix("card") {
+                Some(index) if index.parse::<u32>().is_ok() => index.to_owned(),
+                _ => continue,
+            };
+
+            if card_has_open_stream(&index)? {
+                let name = card.attribute_value("id")
+                    .map(|id| id.to_string_lossy().into_owned())
+                    .unwrap_or_else(|| sysname.into_owned());
+
+                active.push(name);
+            }
+        }
+
+        Ok(active)
+    }
+}
+
+
+/// Find the USB hubs connecting the base to the clipboard.
+///
+/// If `names` is non-empty, only hubs with the given sysfs names (e.g. `1-3`)
+/// are returned. Otherwise, we look for hubs directly on a root hub port
+/// that is marked as removable, which is how the base connector shows up.
+pub fn find_usb_hubs(names: &[String]) -> Result<Vec<udev::Device>> {
+    let mut enumerator = udev::Enumerator::new()
+        .map_err(|source| Error::Io { source })?;
+
+    enumerator.match_subsystem("usb")
+        .map_err(|source| Error::Io { source })?;
+
+    enumerator.match_property("DEVTYPE", "usb_device")
+        .map_err(|source| Error::Io { source })?;
+
+    let devices = enumerator.scan_devices()
+        .map_err(|source| Error::Io { source })?;
+
+    let hubs = devices.filter(|d| {
+        if !names.is_empty() {
+            return names.iter().any(|n| d.sysname() == n.as_str());
+        }
+
+        let is_hub = d.attribute_value("bDeviceClass")
+            .is_some_and(|c| c == USB_CLASS_HUB);
+
+        let is_removable = d.attribute_value("removable")
+            .is_some_and(|r| r == "removable");
+
+        let on_root_hub = d.parent()
+            .is_some_and(|p| p.sysname().to_string_lossy().starts_with("usb"));
+
+        is_hub && is_removable && on_root_hub
+    });
+
+    Ok(hubs.collect())
+}
+
+
+fn card_has_open_stream(index: &str) -> Result<bool> {
+    let dir = PathBuf::from(format!("/proc/asound/card{index}"));
+
+    let entries = match std::fs::read_dir(&dir) {
+        Ok(entries) => entries,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(false),
+        Err(source) => return Err(Error::DeviceAccess { source, device: dir }),
+    };
+
+    for pcm in entries.flatten() {
+        if !pcm.file_name().to_string_lossy().starts_with("pcm") {
+            continue;
+        }
+
+        let subs = match std::fs::read_dir(pcm.path()) {
+            Ok(subs) => subs,
+            Err(_) => continue,
+        };
+
+        for sub in subs.flatten() {
+            let status = std::fs::read_to_string(sub.path().join("status"))
+                .unwrap_or_default();
+
+            if !status.is_empty() && status.trim() != "closed" {
+                return Ok(true);
+            }
+        }
+    }
+
+    Ok(false)
+}
@@ -0,0 +1,147 @@
+use std::path::{Path, PathBuf};
+
+use crate::sys::{Error, Result};
+
+
+/// File with additional or overriding base model names.
+///
+/// Each line consists of the base type (`hid`, `ssh`, or the raw number), the
+/// base ID, and the model name, separated by whitespace, e.g.
+/// `ssh 0x0e Some Keyboard Base`. Lines starting with `#` are ignored.
+pub const OVERRIDE_PATH: &str = "/etc/surface-control/bases";
+
+/// Built-in base models, as (type, ID, name).
+///
+/// Only confirmed IDs go here, everything else should be added via the
+/// override file first.
+const KNOWN_BASES: &[(&str, u8, &str)] = &[];
+
+
+/// Table mapping base types and IDs to model names.
+pub struct Models {
+    entries: Vec<(String, u8, String)>,
+}
+
+impl Models {
+    /// The built-in table, without any overrides.
+    pub fn builtin() -> Self {
+        let entries = KNOWN_BASES.iter()
+            .map(|(ty, id, name)| (ty.to_string(), *id, name.to_string()))
+            .collect();
+
+        Models { entries }
+    }
+
+    /// The built-in table extended by the override file, if present.
+    pub fn load() -> Result<Self> {
+        let mut models = Models::builtin();
+
+        if Path::new(OVERRIDE_PATH).is_file() {
+            models.extend_from(OVERRIDE_PATH)?;
+        }
+
+        Ok(models)
+    }
+
+    /// Add entries from the given file, taking precedence over the existing
+    /// ones.
+    pub fn extend_from<P: AsRef<Path>>(&mut self, path: P) -> Result<()> {
+        let path = path.as_ref();
+
+        let text = std::fs::read_to_string(path)
+            .map_err(|source| Error::DeviceAccess { source, device: path.to_owned() })?;
+
+        for (n, line) in text.lines().enumerate() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+
+            let entry = parse_line(line).ok_or_else(|| {
+                let msg = format!("Invalid entry on line {}: '{line}'", n + 1);
+                Error::DeviceAccess { source: std::io::Error::other(msg), device: PathBuf::from(path) }
+            })?;
+
+            self.entries.insert(0, entry);
+        }
+
+        Ok(())
+    }
+
+    /// Model name of the given base, or its ID in hex if unknown.
+    pub fn name(&self, device_type: sdtx::DeviceType, id: u8) -> String {
+        let ty = device_type_str(device_type);
+
+        self.entries.iter()
+            .find(|(t, i, _)| *t == ty && *i == id)
+            .map(|(_, _, name)| name.clone())
+            .unwrap_or_else(|| format!("{id:#04x}"))
+    }
+}
+
+
+fn device_type_str(device_type: sdtx::DeviceType) -> String {
+    match device_type {
+        sdtx::DeviceType::Hid        => "hid".into(),
+        sdtx::DeviceType::Ssh        => "ssh".into(),
+        sdtx::DeviceType::Unknown(x) => format!("{x:#04x}"),
+    }
+}
+
+fn parse_line(line: &str) -> Option<(String, u8, String)> {
+    let (ty, rest) = line.split_once(char::is_whitespace)?;
+    let (id, name) = rest.trim_start().split_once(char::is_whitespace)?;
+
+    let ty = match ty {
+        "hid" | "ssh" => ty.to_owned(),
+        _             => format!("{:#04x}", parse_u8(ty)?),
+    };
+
+    Some((ty, parse_u8(id)?, name.trim().to_owned()))
+}
+
+/// Parse a number in decimal or, with `0x` prefix, in hex.
+pub fn parse_u8(s: &str) -> Option<u8> {
+    match s.strip_prefix("0x") {
+        Some(hex) => u8::from_str_radix(hex, 16).ok(),
+        None      => s.parse().ok(),
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn temp_path(name: &str) -> PathBuf {
+        std::env::temp_dir().join(format!("surface-test-{name}-{}", std::process::id()))
+    }
+
+    #[test]
+    fn override_file() {
+        let path = temp_path("bases");
+        std::fs::write(&path, "# comment\n\nhid 0x03  Custom Base\n0x05 7 Odd Base\nhid 3 Newer Base\n").unwrap();
+
+        let mut models = Models::builtin();
+        models.extend_from(&path).unwrap();
+        std::fs::remove_file(&path).unwrap();
+
+        // Later entries take precedence.
+        assert_eq!(models.name(sdtx::DeviceType::Hid, 0x03), "Newer Base");
+        assert_eq!(models.name(sdtx::DeviceType::Unknown(0x05), 7), "Odd Base");
+
+        // Unknown bases, including the same ID with a different type.
+        assert_eq!(models.name(sdtx::DeviceType::Ssh, 0x03), "0x03");
+        assert_eq!(models.name(sdtx::DeviceType::Hid, 0x0e), "0x0e");
+    }
+
+    #[test]
+    fn invalid_override_file() {
+        let path = temp_path("bases-invalid");
+        std::fs::write(&path, "ssh 0x0e Custom Base\nusb 1 Invalid\n").unwrap();
+
+        let mut models = Models::builtin();
+        assert!(models.extend_from(&path).is_err());
+        std::fs::remove_file(&path).unwrap();
+    }
+}
@@ -0,0 +1,144 @@
+use std::path::PathBuf;
+
+use crate::sys::{Error, Result};
+
+
+/// ACPI ID of the Surface Book 1/2 dGPU switch platform device.
+pub const ACPI_ID: &str = "MSHW0041";
+
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PowerSwitch {
+    On,
+    Off,
+}
+
+impl std::fmt::Display for PowerSwitch {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::On  => write!(f, "On"),
+            Self::Off => write!(f, "Off"),
+        }
+    }
+}
+
+impl PowerSwitch {
+    fn from_sysfs(value: &str) -> Option<Self> {
+        match value {
+            "1" | "on"  => Some(Self::On),
+            "0" | "off" => Some(Self::Off),
+            _ => None,
+        }
+    }
+
+    fn as_sysfs(&self) -> &'static str {
+        match self {
+            Self::On  => "1",
+            Self::Off => "0",
+        }
+    }
+}
+
+
+/// The explicit dGPU power switch of the Surface Book 1 and 2.
+///
+/// Provided via the `dgpu_power` attribute of the `MSHW0041` platform device,
+/// handled by the `surface_dgpu_sw` and `surface_sb1_dgpu_sw` drivers.
+pub struct Device {
+    path: PathBuf,
+}
+
+impl Device {
+    pub fn open() -> Result<Self> {
+        use std::io;
+
+        let mut enumerator = udev::Enumerator::new()
+            .map_err(|source| Error::Io { source })?;
+
+        enumerator.match_subsystem("platform")
+            .map_err(|source| Error::Io { source })?;
+
+        enumerator.match_sysname(format!("{ACPI_ID}:*"))
+            .map_err(|source| Error::Io { source })?;
+
+        let devices = enumerator.scan_devices()
+            .map_err(|source| Error::Io { source })?;
+
+        for device in devices {
+            let path = device.syspath().join("dgpu_power");
+
+            if path.is_file() {
+                return Ok(Device { path });
+            }
+        }
+
+        Err(Error::DeviceAccess {
+            source: io::Error::new(io::ErrorKind::NotFound, "No dGPU power switch found"),
+            device: PathBuf::from(format!("/sys/bus/platform/devices/{ACPI_ID}:00/dgpu_power")),
+        })
+    }
+
+    /// Get the current switch state.
+    ///
+    /// Returns `None` if the driver does not allow reading back the state.
+    pub fn get(&self) -> Result<Option<PowerSwitch>> {
+        if self.is_write_only() {
+            return Ok(None);
+        }
+
+        let value = std::fs::read_to_string(&self.path)
+            .map_err(|source| Error::DeviceAccess { source, device: self.path.clone() })?;
+
+        let value = value.trim();
+
+        PowerSwitch::from_sysfs(value).map(Some).ok_or_else(|| {
+            let msg = format!("Unexpected value '{value}'");
+            Error::DeviceAccess { source: std::io::Error::other(msg), device: self.path.clone() }
+        })
+    }
+
+    pub fn set(&self, state: PowerSwitch) -> Result<()> {
+        std::fs::write(&self.path, state.as_sysfs())
+            .map_err(|source| Error::DeviceAccess { source, device: self.path.clone() })
+    }
+
+    fn is_write_only(&self) -> bool {
+        use std::os::unix::fs::PermissionsExt;
+
+        std::fs::metadata(&self.path)
+            .map(|m| m.permissions().mode() & 0o444 == 0)
+            .unwrap_or(false)
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn device(name: &str, value: &str) -> Device {
+        let path = std::env::temp_dir().join(format!("surface-test-{name}-{}", std::process::id()));
+        std::fs::write(&path, value).unwrap();
+
+        Device { path }
+    }
+
+    #[test]
+    fn get() {
+        let dev = device("dgpu-power", "1\n");
+        let state = dev.get();
+        std::fs::remove_file(&dev.path).unwrap();
+
+        assert_eq!(state.unwrap(), Some(PowerSwitch::On));
+    }
+
+    #[test]
+    fn get_unexpected() {
+        let dev = device("dgpu-power-invalid", "2\n");
+        let err = dev.get().unwrap_err();
+        std::fs::remove_file(&dev.path).unwrap();
+
+        let source = std::error::Error::source(&err).unwrap();
+        assert_eq!(source.to_string(), "Unexpected value '2'");
+    }
+}
@@ -0,0 +1,110 @@
+use std::sync::{mpsc, Mutex, MutexGuard};
+
+use crate::sys::{Error, Result};
+use crate::sys::dtx::{Backend, Events};
+
+
+/// Backend using the Surface DTX driver.
+pub struct Hardware {
+    device: Mutex<sdtx::Device>,
+}
+
+impl Hardware {
+    pub fn open() -> Result<Self> {
+        let device = sdtx::Device::open()?;
+        Ok(Hardware { device: Mutex::new(device) })
+    }
+
+    fn device(&self) -> MutexGuard<'_, sdtx::Device> {
+        self.device.lock().unwrap()
+    }
+}
+
+impl Backend for Hardware {
+    fn latch_lock(&self) -> Result<()> {
+        Ok(self.device().latch_lock()?)
+    }
+
+    fn latch_unlock(&self) -> Result<()> {
+        Ok(self.device().latch_unlock()?)
+    }
+
+    fn latch_request(&self) -> Result<()> {
+        Ok(self.device().latch_request()?)
+    }
+
+    fn latch_confirm(&self) -> Result<()> {
+        Ok(self.device().latch_confirm()?)
+    }
+
+    fn latch_heartbeat(&self) -> Result<()> {
+        Ok(self.device().latch_heartbeat()?)
+    }
+
+    fn latch_cancel(&self) -> Result<()> {
+        Ok(self.device().latch_cancel()?)
+    }
+
+    fn get_base_info(&self) -> Result<sdtx::BaseInfo> {
+        Ok(self.device().get_base_info()?)
+    }
+
+    fn get_device_mode(&self) -> Result<sdtx::DeviceMode> {
+        Ok(self.device().get_device_mode()?)
+    }
+
+    fn get_latch_status(&self) -> Result<sdtx::LatchStatus> {
+        Ok(self.device().get_latch_status()?)
+    }
+
+    fn events(&self) -> Result<Events> {
+        let (tx, rx) = mpsc::channel();
+        let (setup_tx, setup_rx) = mpsc::channel();
+
+        // The event stream borrows the device, so read it on a thread owning
+        // a device of its own.
+        std::thread::spawn(move || {
+            let mut device = match sdtx::Device::open() {
+                Ok(device) => device,
+                Err(e) => {
+                    let _ = setup_tx.send(Err(Error::from(e)));
+                    return;
+                },
+            };
+
+            let events = match device.events() {
+                Ok(events) => events,
+                Err(e) => {
+                    let source = std::io::Error::other(e);
+                    let _ = setup_tx.send(Err(Error::Io { source }));
+                    return;
+                },
+            };
+
+            let _ = setup_tx.send(Ok(()));
+
+            for event in events {
+                let event = event.map_err(|source| Error::Io { source });
+
+                if tx.send(event).is_err() {
+                    break;
+                }
+            }
+        });
+
+        match setup_rx.recv() {
+            Ok(result) => result?,
+            Err(_) => {
+                let source = std::io::Error::other("Event reader terminated unexpectedly");
+                return Err(Error::Io { source });
+            },
+        }
+
+        Ok(rx)
+    }
+
+    fn reopen(&self) -> Result<()> {
+        *self.device() = sdtx::Device::open()?;
+        Ok(())
+    }
+}
@@ -0,0 +1,527 @@
+use std::path::PathBuf;
+use std::sync::{mpsc, Arc, Mutex, MutexGuard, Weak};
+use std::time::{Duration, Instant};
+
+use crate::sys::{Error, Result};
+use crate::sys::bases::parse_u8;
+use crate::sys::dtx::{Backend, Events};
+
+
+const TICK_INTERVAL: Duration = Duration::from_millis(10);
+
+/// Device path reported in errors while unplugged.
+const DEVICE_PATH: &str = "/dev/surface/dtx";
+
+
+/// In-memory backend simulating the EC.
+///
+/// Detachment requests are canceled if they are not confirmed or kept alive
+/// via heartbeats within the request timeout. Once opened, the latch closes
+/// again if the base has not been removed within the open timeout. Aborting
+/// or canceling a request does not generate an event.
+///
+/// The user side (pressing the detach button, removing or attaching the
+/// base, ...) can be simulated via the respective methods or a script, see
+/// [`Mock::from_script`].
+#[derive(Clone)]
+pub struct Mock {
+    state: Arc<Mutex<State>>,
+}
+
+impl Mock {
+    /// Create a new mock with the base attached and the device in laptop
+    /// mode.
+    pub fn new() -> Self {
+        let state = Arc::new(Mutex::new(State::new()));
+        let weak = Arc::downgrade(&state);
+
+        std::thread::spawn(move || tick(weak));
+
+        Mock { state }
+    }
+
+    /// Create a new mock running the script at the given path.
+    ///
+    /// Scripts consist of one step per line, with `#` starting a comment:
+    ///
+    /// - `wait MS`: pause for the given number of milliseconds,
+    /// - `press`: press the detach button,
+    /// - `detach`: remove the base,
+    /// - `attach [hid|ssh] [ID]`: attach a base,
+    /// - `mode tablet|laptop|studio`: change the device mode,
+    /// - `request-timeout MS`, `open-timeout MS`: change EC timeouts,
+    /// - `unplug MS`: make the device unavailable for the given time.
+    ///
+    /// Steps before the first `wait` set up the initial state and run
+    /// immediately, the others on a separate thread.
+    pub fn from_script<P: Into<PathBuf>>(path: P) -> Result<Self> {
+        let path = path.into();
+
+        let text = std::fs::read_to_string(&path)
+            .map_err(|source| Error::DeviceAccess { source, device: path.clone() })?;
+
+        let mut steps = Vec::new();
+        for (n, line) in text.lines().enumerate() {
+            let line = line.split('#').next().unwrap_or_default().trim();
+            if line.is_empty() {
+                continue;
+            }
+
+            let step = Step::parse(line).ok_or_else(|| {
+                let msg = format!("Invalid mock script step on line {}: '{line}'", n + 1);
+                Error::DeviceAccess { source: std::io::Error::other(msg), device: path.clone() }
+            })?;
+
+            steps.push(step);
+        }
+
+        let mock = Mock::new();
+
+        let first_wait = steps.iter()
+            .position(|s| matches!(s, Step::Wait(_)))
+            .unwrap_or(steps.len());
+
+        let rest = steps.split_off(first_wait);
+        for step in steps {
+            mock.run(step);
+        }
+
+        let runner = mock.clone();
+        std::thread::spawn(move || {
+            for step in rest {
+                runner.run(step);
+            }
+        });
+
+        Ok(mock)
+    }
+
+    /// Simulate a press of the detach button.
+    pub fn press(&self) {
+        self.state.lock().unwrap().request();
+    }
+
+    /// Simulate removal of the base.
+    pub fn detach(&self) {
+        self.state.lock().unwrap().detach();
+    }
+
+    /// Simulate attaching a base.
+    pub fn attach(&self, device_type: sdtx::DeviceType, id: u8) {
+        self.state.lock().unwrap().attach(device_type, id);
+    }
+
+    pub fn set_device_mode(&self, mode: sdtx::DeviceMode) {
+        self.state.lock().unwrap().set_device_mode(mode);
+    }
+
+    /// Whether the latch is currently locked.
+    #[cfg(test)]
+    pub fn is_locked(&self) -> bool {
+        self.state.lock().unwrap().locked
+    }
+
+    pub fn set_request_timeout(&self, timeout: Duration) {
+        self.state.lock().unwrap().request_timeout = timeout;
+    }
+
+    pub fn set_open_timeout(&self, timeout: Duration) {
+        self.state.lock().unwrap().open_timeout = timeout;
+    }
+
+    /// Simulate the device going away for the given time, e.g. due to a
+    /// module reload. Open event streams fail and are closed.
+    pub fn unplug(&self, duration: Duration) {
+        self.state.lock().unwrap().unplug(duration);
+    }
+
+    /// Number of attempts to reopen the device so far, successful or not.
+    #[cfg(test)]
+    pub fn reopens(&self) -> u32 {
+        self.state.lock().unwrap().reopens
+    }
+
+    fn run(&self, step: Step) {
+        match step {
+            Step::Wait(duration)    => std::thread::sleep(duration),
+            Step::Press             => self.press(),
+            Step::Detach            => self.detach(),
+            Step::Attach(ty, id)    => self.attach(ty, id),
+            Step::Mode(mode)        => self.set_device_mode(mode),
+            Step::RequestTimeout(t) => self.set_request_timeout(t),
+            Step::OpenTimeout(t)    => self.set_open_timeout(t),
+            Step::Unplug(t)         => self.unplug(t),
+        }
+    }
+}
+
+impl Default for Mock {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Backend for Mock {
+    fn latch_lock(&self) -> Result<()> {
+        self.available()?.locked = true;
+        Ok(())
+    }
+
+    fn latch_unlock(&self) -> Result<()> {
+        self.available()?.locked = false;
+        Ok(())
+    }
+
+    fn latch_request(&self) -> Result<()> {
+        self.available()?.request();
+        Ok(())
+    }
+
+    fn latch_confirm(&self) -> Result<()> {
+        self.available()?.confirm();
+        Ok(())
+    }
+
+    fn latch_heartbeat(&self) -> Result<()> {
+        self.available()?.heartbeat();
+        Ok(())
+    }
+
+    fn latch_cancel(&self) -> Result<()> {
+        self.available()?.cancel();
+        Ok(())
+    }
+
+    fn get_base_info(&self) -> Result<sdtx::BaseInfo> {
+        let state = self.available()?;
+
+        Ok(sdtx::BaseInfo {
+            state: state.base,
+            device_type: state.device_type,
+            id: state.id,
+        })
+    }
+
+    fn get_device_mode(&self) -> Result<sdtx::DeviceMode> {
+        Ok(self.available()?.mode)
+    }
+
+    fn get_latch_status(&self) -> Result<sdtx::LatchStatus> {
+        match self.available()?.latch {
+            Latch::Opened { .. } => Ok(sdtx::LatchStatus::Opened),
+            _                    => Ok(sdtx::LatchStatus::Closed),
+        }
+    }
+
+    fn events(&self) -> Result<Events> {
+        let (tx, rx) = mpsc::channel();
+        self.available()?.subscribers.push(tx);
+        Ok(rx)
+    }
+
+    fn reopen(&self) -> Result<()> {
+        self.state.lock().unwrap().reopens += 1;
+        self.available().map(|_| ())
+    }
+}
+
+impl Mock {
+    /// Lock the state, failing if the device is currently unplugged.
+    fn available(&self) -> Result<MutexGuard<'_, State>> {
+        let state = self.state.lock().unwrap();
+
+        match state.unplugged {
+            Some(until) if Instant::now() < until => Err(Error::DeviceAccess {
+                source: std::io::ErrorKind::NotFound.into(),
+                device: PathBuf::from(DEVICE_PATH),
+            }),
+            _ => Ok(state),
+        }
+    }
+}
+
+
+enum Latch {
+    Closed,
+    Requested { deadline: Instant },
+    Opened { deadline: Instant },
+}
+
+struct State {
+    base: sdtx::BaseState,
+    device_type: sdtx::DeviceType,
+    id: u8,
+    mode: sdtx::DeviceMode,
+    latch: Latch,
+    locked: bool,
+    request_timeout: Duration,
+    open_timeout: Duration,
+    subscribers: Vec<mpsc::Sender<Result<sdtx::Event>>>,
+    unplugged: Option<Instant>,
+    reopens: u32,
+}
+
+impl State {
+    fn new() -> Self {
+        State {
+            base: sdtx::BaseState::Attached,
+            device_type: sdtx::DeviceType::Hid,
+            id: 0,
+            mode: sdtx::DeviceMode::Laptop,
+            latch: Latch::Closed,
+            locked: false,
+            request_timeout: Duration::from_secs(5),
+            open_timeout: Duration::from_secs(10),
+            subscribers: Vec::new(),
+            unplugged: None,
+            reopens: 0,
+        }
+    }
+
+    /// Send an event to all subscribers.
+    fn emit<F>(&mut self, event: F)
+    where
+        F: Fn() -> sdtx::Event,
+    {
+        self.subscribers.retain(|tx| tx.send(Ok(event())).is_ok());
+    }
+
+    fn emit_latch_status(&mut self, opened: bool) {
+        use sdtx::event::LatchStatus;
+
+        self.emit(|| sdtx::Event::LatchStatus {
+            status: if opened { LatchStatus::Opened } else { LatchStatus::Closed },
+        });
+    }
+
+    fn request(&mut self) {
+        use sdtx::event::CancelReason;
+
+        match self.latch {
+            Latch::Closed => {
+                let feasible = matches!(self.base, sdtx::BaseState::Attached) && !self.locked;
+
+                if feasible {
+                    self.latch = Latch::Requested { deadline: Instant::now() + self.request_timeout };
+                    self.emit(|| sdtx::Event::Request);
+                } else {
+                    self.emit(|| sdtx::Event::Cancel {
+                        reason: CancelReason::Runtime(sdtx::RuntimeError::NotFeasible),
+                    });
+                }
+            },
+            Latch::Requested { .. } => {
+                self.latch = Latch::Closed;
+            },
+            Latch::Opened { .. } => (),
+        }
+    }
+
+    fn heartbeat(&mut self) {
+        if let Latch::Requested { .. } = self.latch {
+            self.latch = Latch::Requested { deadline: Instant::now() + self.request_timeout };
+        }
+    }
+
+    fn confirm(&mut self) {
+        if let Latch::Requested { .. } = self.latch {
+            self.latch = Latch::Opened { deadline: Instant::now() + self.open_timeout };
+            self.emit_latch_status(true);
+        }
+    }
+
+    fn cancel(&mut self) {
+        match self.latch {
+            Latch::Closed           => (),
+            Latch::Requested { .. } => self.latch = Latch::Closed,
+            Latch::Opened { .. }    => {
+                self.latch = Latch::Closed;
+                self.emit_latch_status(false);
+            },
+        }
+    }
+
+    fn detach(&mut self) {
+        use sdtx::event::BaseState;
+
+        if let Latch::Opened { .. } = self.latch {
+            self.latch = Latch::Closed;
+            self.emit_latch_status(false);
+        } else {
+            self.latch = Latch::Closed;
+        }
+
+        self.base = sdtx::BaseState::Detached;
+
+        let (device_type, id) = (self.device_type, self.id);
+        self.emit(|| sdtx::Event::BaseConnection { state: BaseState::Detached, device_type, id });
+
+        self.set_device_mode(sdtx::DeviceMode::Tablet);
+    }
+
+    fn attach(&mut self, device_type: sdtx::DeviceType, id: u8) {
+        use sdtx::event::BaseState;
+
+        self.base = sdtx::BaseState::Attached;
+        self.device_type = device_type;
+        self.id = id;
+
+        self.emit(|| sdtx::Event::BaseConnection { state: BaseState::Attached, device_type, id });
+
+        self.set_device_mode(sdtx::DeviceMode::Laptop);
+    }
+
+    fn set_device_mode(&mut self, mode: sdtx::DeviceMode) {
+        use sdtx::event::DeviceMode;
+
+        self.mode = mode;
+
+        self.emit(|| sdtx::Event::DeviceMode {
+            mode: match mode {
+                sdtx::DeviceMode::Tablet => DeviceMode::Tablet,
+                sdtx::DeviceMode::Laptop => DeviceMode::Laptop,
+                sdtx::DeviceMode::Studio => DeviceMode::Studio,
+            },
+        });
+    }
+
+    fn unplug(&mut self, duration: Duration) {
+        self.unplugged = Some(Instant::now() + duration);
+
+        for tx in self.subscribers.drain(..) {
+            let _ = tx.send(Err(Error::Io { source: nix::errno::Errno::ENODEV.into() }));
+        }
+    }
+
+    /// Handle EC timeouts.
+    fn tick(&mut self, now: Instant) {
+        use sdtx::event::CancelReason;
+
+        match self.latch {
+            Latch::Requested { deadline } if now >= deadline => {
+                self.latch = Latch::Closed;
+                self.emit(|| sdtx::Event::Cancel {
+                    reason: CancelReason::Runtime(sdtx::RuntimeError::Timeout),
+                });
+            },
+            Latch::Opened { deadline } if now >= deadline => {
+                self.latch = Latch::Closed;
+                self.emit_latch_status(false);
+            },
+            _ => (),
+        }
+    }
+}
+
+
+fn tick(state: Weak<Mutex<State>>) {
+    while let Some(state) = state.upgrade() {
+        state.lock().unwrap().tick(Instant::now());
+        drop(state);
+
+        std::thread::sleep(TICK_INTERVAL);
+    }
+}
+
+
+enum Step {
+    Wait(Duration),
+    Press,
+    Detach,
+    Attach(sdtx::DeviceType, u8),
+    Mode(sdtx::DeviceMode),
+    RequestTimeout(Duration),
+    OpenTimeout(Duration),
+    Unplug(Duration),
+}
+
+impl Step {
+    fn parse(line: &str) -> Option<Self> {
+        let mut args = line.split_whitespace();
+        let command = args.next()?;
+        let args: Vec<&str> = args.collect();
+
+        let millis = |s: &str| s.parse().ok().map(Duration::from_millis);
+
+        let step = match (command, args.as_slice()) {
+            ("wait",            [ms])   => Step::Wait(millis(ms)?),
+            ("press",           [])     => Step::Press,
+            ("detach",          [])     => Step::Detach,
+            ("attach",          args)   => {
+                let ty = match args.first() {
+                    Some(&"hid") | None => sdtx::DeviceType::Hid,
+                    Some(&"ssh")        => sdtx::DeviceType::Ssh,
+                    Some(_)             => return None,
+                };
+
+                let id = match args.get(1) {
+                    Some(id) => parse_u8(id)?,
+                    None     => 0,
+                };
+
+                if args.len() > 2 {
+                    return None;
+                }
+
+                Step::Attach(ty, id)
+            },
+            ("mode",            [mode]) => match *mode {
+                "tablet" => Step::Mode(sdtx::DeviceMode::Tablet),
+                "laptop" => Step::Mode(sdtx::DeviceMode::Laptop),
+                "studio" => Step::Mode(sdtx::DeviceMode::Studio),
+                _        => return None,
+            },
+            ("request-timeout", [ms])   => Step::RequestTimeout(millis(ms)?),
+            ("open-timeout",    [ms])   => Step::OpenTimeout(millis(ms)?),
+            ("unplug",          [ms])   => Step::Unplug(millis(ms)?),
+            _                           => return None,
+        };
+
+        Some(step)
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn script() {
+        let path = std::env::temp_dir().join(format!("surface-test-mock-{}", std::process::id()));
+        std::fs::write(&path, "attach ssh 0x0e  # initial state\nmode studio\nwait 50\ndetach\n").unwrap();
+
+        let mock = Mock::from_script(&path).unwrap();
+        std::fs::remove_file(&path).unwrap();
+
+        let info = mock.get_base_info().unwrap();
+        assert_eq!(info.device_type, sdtx::DeviceType::Ssh);
+        assert_eq!(info.id, 0x0e);
+        assert_eq!(mock.get_device_mode().unwrap(), sdtx::DeviceMode::Studio);
+
+        let events = mock.events().unwrap();
+        let event = events.recv_timeout(Duration::from_secs(1)).unwrap().unwrap();
+
+        assert!(matches!(event, sdtx::Event::BaseConnection { state: sdtx::event::BaseState::Detached, .. }));
+    }
+
+    #[test]
+    fn invalid_script() {
+        assert!(Step::parse("attach usb").is_none());
+        assert!(Step::parse("wait").is_none());
+        assert!(Step::parse("mode closed").is_none());
+        assert!(Step::parse("unplug 100").is_some());
+    }
+
+    #[test]
+    fn request_timeout() {
+        let mock = Mock::new();
+        mock.set_request_timeout(Duration::from_millis(50));
+
+        let events = mock.events().unwrap();
+        mock.press();
+
+        assert!(matches!(events.recv().unwrap().unwrap(), sdtx::Event::Request));
+        assert!(matches!(events.recv().unwrap().unwrap(), sdtx::Event::Cancel { .. }));
+    }
+}
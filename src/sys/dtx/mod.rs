@@ -0,0 +1,59 @@
+mod hardware;
+
+#[cfg(any(test, feature = "mock"))]
+mod mock;
+
+pub use hardware::Hardware;
+
+#[cfg(any(test, feature = "mock"))]
+pub use mock::Mock;
+
+use std::sync::mpsc;
+
+use crate::sys::Result;
+
+
+/// Environment variable pointing to a script for the mock backend. If set,
+/// [`open`] returns a [`Mock`] running that script instead of accessing the
+/// hardware. Only available with the `mock` feature.
+#[cfg(feature = "mock")]
+pub const MOCK_ENV: &str = "SURFACE_DTX_MOCK";
+
+
+/// Stream of DTX events, as returned by [`Backend::events`].
+pub type Events = mpsc::Receiver<Result<sdtx::Event>>;
+
+
+/// Operations of the Surface DTX (clipboard detachment) system.
+pub trait Backend {
+    fn latch_lock(&self) -> Result<()>;
+    fn latch_unlock(&self) -> Result<()>;
+    fn latch_request(&self) -> Result<()>;
+    fn latch_confirm(&self) -> Result<()>;
+    fn latch_heartbeat(&self) -> Result<()>;
+    fn latch_cancel(&self) -> Result<()>;
+
+    fn get_base_info(&self) -> Result<sdtx::BaseInfo>;
+    fn get_device_mode(&self) -> Result<sdtx::DeviceMode>;
+    fn get_latch_status(&self) -> Result<sdtx::LatchStatus>;
+
+    /// Subscribe to events. All events occurring after this call are sent
+    /// to the returned channel.
+    fn events(&self) -> Result<Events>;
+
+    /// Open the device again, e.g. after it has been removed on resume or
+    /// by reloading the module.
+    fn reopen(&self) -> Result<()>;
+}
+
+
+/// Open the DTX backend, i.e. the hardware or, if built with the `mock`
+/// feature and [`MOCK_ENV`] is set, a mock running the given script.
+pub fn open() -> Result<Box<dyn Backend>> {
+    #[cfg(feature = "mock")]
+    if let Some(script) = std::env::var_os(MOCK_ENV) {
+        return Ok(Box::new(Mock::from_script(script)?));
+    }
+
+    Ok(Box::new(Hardware::open()?))
+}
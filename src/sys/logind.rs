@@ -0,0 +1,136 @@
+use std::sync::mpsc;
+use std::time::Duration;
+
+use dbus::arg::OwnedFd;
+use dbus::blocking::Connection;
+use dbus::message::MatchRule;
+
+use crate::sys::{Error, Result};
+
+
+const DEST: &str = "org.freedesktop.login1";
+const PATH: &str = "/org/freedesktop/login1";
+const IFACE_MANAGER: &str = "org.freedesktop.login1.Manager";
+const IFACE_SESSION: &str = "org.freedesktop.login1.Session";
+
+const CALL_TIMEOUT: Duration = Duration::from_secs(5);
+
+
+/// Entry returned by `ListSessions`: ID, user ID, user name, seat and object
+/// path of a session.
+type SessionEntry = (String, u32, String, String, dbus::Path<'static>);
+
+
+/// Signals emitted by logind.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Signal {
+    /// The system is about to suspend (`true`) or has resumed (`false`).
+    PrepareForSleep(bool),
+
+    /// The session with the given object path should be locked.
+    Lock { session: String },
+
+    /// The session with the given object path should be unlocked.
+    Unlock { session: String },
+}
+
+
+/// An inhibitor lock taken via logind, released on drop.
+pub struct InhibitorLock {
+    _fd: OwnedFd,
+}
+
+
+/// Client for the logind manager on the system bus.
+pub struct Logind {
+    conn: Connection,
+    tx: mpsc::Sender<Signal>,
+    signals: mpsc::Receiver<Signal>,
+}
+
+impl Logind {
+    pub fn connect() -> Result<Self> {
+        let conn = Connection::new_system()
+            .map_err(|source| Error::DBus { source })?;
+
+        Logind::with_connection(conn)
+    }
+
+    /// Use the given connection instead of the system bus, e.g. a private
+    /// `dbus-daemon` for testing.
+    pub fn with_connection(conn: Connection) -> Result<Self> {
+        let (tx, signals) = mpsc::channel();
+
+        let rule = MatchRule::new_signal(IFACE_MANAGER, "PrepareForSleep");
+        let sender = tx.clone();
+        conn.add_match(rule, move |(start,): (bool,), _, _| {
+            sender.send(Signal::PrepareForSleep(start)).is_ok()
+        }).map_err(|source| Error::DBus { source })?;
+
+        Ok(Logind { conn, tx, signals })
+    }
+
+    /// Also receive lock and unlock requests of all sessions.
+    pub fn watch_sessions(&self) -> Result<()> {
+        let rule = MatchRule::new_signal(IFACE_SESSION, "Lock");
+        let sender = self.tx.clone();
+        self.conn.add_match(rule, move |(): (), _, msg| {
+            let session = msg.path().map(|p| p.to_string()).unwrap_or_default();
+            sender.send(Signal::Lock { session }).is_ok()
+        }).map_err(|source| Error::DBus { source })?;
+
+        let rule = MatchRule::new_signal(IFACE_SESSION, "Unlock");
+        let sender = self.tx.clone();
+        self.conn.add_match(rule, move |(): (), _, msg| {
+            let session = msg.path().map(|p| p.to_string()).unwrap_or_default();
+            sender.send(Signal::Unlock { session }).is_ok()
+        }).map_err(|source| Error::DBus { source })?;
+
+        Ok(())
+    }
+
+    /// Object paths of all sessions that are currently locked.
+    pub fn locked_sessions(&self) -> Result<Vec<String>> {
+        use dbus::blocking::stdintf::org_freedesktop_dbus::Properties;
+
+        let (sessions,): (Vec<SessionEntry>,) = self.conn
+            .with_proxy(DEST, PATH, CALL_TIMEOUT)
+            .method_call(IFACE_MANAGER, "ListSessions", ())
+            .map_err(|source| Error::DBus { source })?;
+
+        let mut locked = Vec::new();
+        for (_, _, _, _, path) in sessions {
+            let hint: bool = self.conn.with_proxy(DEST, &path, CALL_TIMEOUT)
+                .get(IFACE_SESSION, "LockedHint")
+                .map_err(|source| Error::DBus { source })?;
+
+            if hint {
+                locked.push(path.to_string());
+            }
+        }
+
+        Ok(locked)
+    }
+
+    /// Take a delay inhibitor lock for sleep, giving us time to react to
+    /// `PrepareForSleep(true)` until the lock is dropped.
+    pub fn inhibit_sleep(&self, who: &str, why: &str) -> Result<InhibitorLock> {
+        let (fd,): (OwnedFd,) = self.conn.with_proxy(DEST, PATH, CALL_TIMEOUT)
+            .method_call(IFACE_MANAGER, "Inhibit", ("sleep", who, why, "delay"))
+            .map_err(|source| Error::DBus { source })?;
+
+        Ok(InhibitorLock { _fd: fd })
+    }
+
+    /// Wait up to `timeout` for incoming messages and return the signals
+    /// received so far.
+    pub fn poll(&self, timeout: Duration) -> Result<Vec<Signal>> {
+        self.conn.process(timeout)
+            .map_err(|source| Error::DBus { source })?;
+
+        // Handle anything else that is already queued without waiting.
+        while self.conn.process(Duration::ZERO).map_err(|source| Error::DBus { source })? {}
+
+        Ok(self.signals.try_iter().collect())
+    }
+}
@@ -1,5 +1,23 @@
+pub mod base;
+pub mod bases;
+pub mod dgpu_sw;
+pub mod dtx;
+pub mod logind;
+pub mod mounts;
+pub mod notifications;
+pub mod nvidia;
 pub mod pci;
+pub mod pciids;
+pub mod pidfile;
+pub mod proc;
 pub mod profile;
+pub mod rules;
+pub mod sensors;
+pub mod signal;
+pub mod storage;
+
+#[cfg(test)]
+pub mod testbus;
 
 use thiserror::Error;
 
@@ -17,8 +35,14 @@ pub enum Error {
     #[error("DTX subsystem error")]
     Dtx { source: sdtx::ProtocolError },
 
+    #[error("D-Bus error")]
+    DBus { source: dbus::Error },
+
     #[error("SysFS error")]
     SysFs { source: pci::SysFsError },
+
+    #[error("Command \"{command}\" failed ({status})")]
+    Command { command: &'static str, status: std::process::ExitStatus },
 }
 
 pub type Result<T> = std::result::Result<T, Error>;
@@ -0,0 +1,74 @@
+use std::path::PathBuf;
+
+use crate::sys::{Error, Result};
+
+
+/// A mounted filesystem, as listed in `/proc/self/mountinfo`.
+#[derive(Debug, Clone)]
+pub struct Mount {
+    /// Device number as `major:minor`.
+    pub dev: String,
+    pub mountpoint: PathBuf,
+    pub source: String,
+}
+
+
+pub fn mounts() -> Result<Vec<Mount>> {
+    let path = PathBuf::from("/proc/self/mountinfo");
+
+    let text = std::fs::read_to_string(&path)
+        .map_err(|source| Error::DeviceAccess { source, device: path })?;
+
+    Ok(text.lines().filter_map(parse_line).collect())
+}
+
+/// Mounts of the block device with the given `major:minor` number.
+pub fn mounts_of(dev: &str) -> Result<Vec<Mount>> {
+    Ok(mounts()?.into_iter().filter(|m| m.dev == dev).collect())
+}
+
+
+fn parse_line(line: &str) -> Option<Mount> {
+    // Format: ID PARENT MAJ:MIN ROOT MOUNTPOINT OPTIONS [OPTIONAL...] - FSTYPE SOURCE SUPEROPTIONS
+    let (head, tail) = line.split_once(" - ")?;
+
+    let mut head = head.split(' ');
+    let dev = head.nth(2)?;
+    let mountpoint = head.nth(1)?;
+
+    let mut tail = tail.split(' ');
+    let source = tail.nth(1)?;
+
+    Some(Mount {
+        dev: dev.to_owned(),
+        mountpoint: PathBuf::from(unescape(mountpoint)),
+        source: unescape(source),
+    })
+}
+
+/// Decode octal escapes (e.g. `\040` for space) used in mountinfo.
+fn unescape(s: &str) -> String {
+    let bytes = s.as_bytes();
+    let mut out = Vec::with_capacity(bytes.len());
+
+    let mut i = 0;
+    while i < bytes.len() {
+        let code = bytes.get(i + 1..i + 4)
+            .filter(|_| bytes[i] == b'\\')
+            .and_then(|c| std::str::from_utf8(c).ok())
+            .and_then(|c| u8::from_str_radix(c, 8).ok());
+
+        match code {
+            Some(code) => {
+                out.push(code);
+                i += 4;
+            },
+            None => {
+                out.push(bytes[i]);
+                i += 1;
+            },
+        }
+    }
+
+    String::from_utf8_lossy(&out).into_owned()
+}
@@ -0,0 +1,275 @@
+use std::collections::HashMap;
+use std::convert::TryInto;
+use std::sync::mpsc;
+use std::time::Duration;
+
+use dbus::arg::Variant;
+use dbus::blocking::Connection;
+use dbus::message::MatchRule;
+
+use crate::sys::{Error, Result};
+
+
+const DEST: &str = "org.freedesktop.Notifications";
+const PATH: &str = "/org/freedesktop/Notifications";
+const IFACE: &str = "org.freedesktop.Notifications";
+
+const CALL_TIMEOUT: Duration = Duration::from_secs(5);
+
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Urgency {
+    Low,
+    Normal,
+    Critical,
+}
+
+impl Urgency {
+    fn value(self) -> u8 {
+        match self {
+            Urgency::Low      => 0,
+            Urgency::Normal   => 1,
+            Urgency::Critical => 2,
+        }
+    }
+}
+
+
+/// A notification to be shown.
+#[derive(Debug, Clone)]
+pub struct Notification {
+    pub summary: String,
+    pub body: String,
+    pub urgency: Urgency,
+
+    /// Actions as (key, label) pairs.
+    pub actions: Vec<(String, String)>,
+
+    /// Expiration timeout, `None` for the server default, zero for never.
+    pub timeout: Option<Duration>,
+}
+
+impl Notification {
+    pub fn new(summary: impl Into<String>, body: impl Into<String>) -> Self {
+        Notification {
+            summary: summary.into(),
+            body: body.into(),
+            urgency: Urgency::Normal,
+            actions: Vec::new(),
+            timeout: None,
+        }
+    }
+}
+
+
+/// Signals emitted by the notification server.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Signal {
+    ActionInvoked { id: u32, action: String },
+    Closed { id: u32 },
+}
+
+
+/// Client for the freedesktop notification service on the session bus.
+pub struct Notifications {
+    conn: Connection,
+    app_name: String,
+    signals: mpsc::Receiver<Signal>,
+}
+
+impl Notifications {
+    pub fn connect(app_name: &str) -> Result<Self> {
+        let conn = Connection::new_session()
+            .map_err(|source| Error::DBus { source })?;
+
+        Notifications::with_connection(conn, app_name)
+    }
+
+    /// Use the given connection instead of the session bus, e.g. a private
+    /// `dbus-daemon` for testing.
+    pub fn with_connection(conn: Connection, app_name: &str) -> Result<Self> {
+        let (tx, signals) = mpsc::channel();
+
+        let rule = MatchRule::new_signal(IFACE, "ActionInvoked");
+        let sender = tx.clone();
+        conn.add_match(rule, move |(id, action): (u32, String), _, _| {
+            sender.send(Signal::ActionInvoked { id, action }).is_ok()
+        }).map_err(|source| Error::DBus { source })?;
+
+        let rule = MatchRule::new_signal(IFACE, "NotificationClosed");
+        conn.add_match(rule, move |(id, _reason): (u32, u32), _, _| {
+            tx.send(Signal::Closed { id }).is_ok()
+        }).map_err(|source| Error::DBus { source })?;
+
+        Ok(Notifications { conn, app_name: app_name.to_owned(), signals })
+    }
+
+    /// Show a notification, replacing the one with ID `replaces` if non-zero.
+    /// Returns the ID of the new notification.
+    pub fn notify(&self, replaces: u32, notification: &Notification) -> Result<u32> {
+        let actions: Vec<&str> = notification.actions.iter()
+            .flat_map(|(key, label)| [key.as_str(), label.as_str()])
+            .collect();
+
+        let mut hints = HashMap::new();
+        hints.insert("urgency", Variant(notification.urgency.value()));
+
+        let timeout = match notification.timeout {
+            Some(timeout) => timeout.as_millis().try_into().unwrap_or(i32::MAX),
+            None          => -1,
+        };
+
+        let args = (
+            self.app_name.as_str(),
+            replaces,
+            "",
+            notification.summary.as_str(),
+            notification.body.as_str(),
+            actions,
+            hints,
+            timeout,
+        );
+
+        let (id,): (u32,) = self.proxy()
+            .method_call(IFACE, "Notify", args)
+            .map_err(|source| Error::DBus { source })?;
+
+        Ok(id)
+    }
+
+    /// Wait up to `timeout` for incoming messages and return the signals
+    /// received so far.
+    pub fn poll(&self, timeout: Duration) -> Result<Vec<Signal>> {
+        self.conn.process(timeout)
+            .map_err(|source| Error::DBus { source })?;
+
+        // Handle anything else that is already queued without waiting.
+        while self.conn.process(Duration::ZERO).map_err(|source| Error::DBus { source })? {}
+
+        Ok(self.signals.try_iter().collect())
+    }
+
+    fn proxy(&self) -> dbus::blocking::Proxy<'_, &Connection> {
+        self.conn.with_proxy(DEST, PATH, CALL_TIMEOUT)
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::sys::testbus::TestBus;
+
+    use dbus::arg::PropMap;
+    use dbus::channel::{MatchingReceiver, Sender};
+    use dbus::message::Message;
+
+    /// Notify call as received by the server.
+    #[derive(Debug, PartialEq)]
+    struct Call {
+        app_name: String,
+        replaces: u32,
+        summary: String,
+        body: String,
+        actions: Vec<String>,
+        urgency: Option<u64>,
+        timeout: i32,
+    }
+
+    /// Run a stub notification server, returning IDs counting up from 1.
+    fn serve(bus: &TestBus) -> mpsc::Receiver<Call> {
+        let conn = bus.connect();
+        conn.request_name(DEST, false, true, false).unwrap();
+
+        let (tx, calls) = mpsc::channel();
+        let mut next_id = 1;
+
+        conn.start_receive(MatchRule::new_method_call(), Box::new(move |msg: Message, conn: &Connection| {
+            if msg.member().as_deref() != Some("Notify") {
+                return true;
+            }
+
+            let (app_name, replaces, _, summary, body, actions, hints, timeout):
+                (String, u32, String, String, String, Vec<String>, PropMap, i32) = msg.read_all().unwrap();
+
+            let urgency = hints.get("urgency").and_then(|v| v.0.as_u64());
+            tx.send(Call { app_name, replaces, summary, body, actions, urgency, timeout }).unwrap();
+
+            let id = if replaces != 0 { replaces } else { next_id };
+            next_id += 1;
+
+            conn.send(msg.method_return().append1(id)).unwrap();
+            true
+        }));
+
+        // Stops once the daemon is gone.
+        std::thread::spawn(move || while conn.process(Duration::from_millis(50)).is_ok() {});
+
+        calls
+    }
+
+    /// Poll until the given number of signals has been received.
+    fn wait_for_signals(client: &Notifications, count: usize) -> Vec<Signal> {
+        let mut signals = Vec::new();
+
+        for _ in 0..50 {
+            signals.extend(client.poll(Duration::from_millis(100)).unwrap());
+            if signals.len() >= count {
+                break;
+            }
+        }
+
+        signals
+    }
+
+    #[test]
+    fn notify_and_signals() {
+        let bus = match TestBus::spawn() {
+            Some(bus) => bus,
+            None => return eprintln!("dbus-daemon not available, skipping"),
+        };
+
+        let calls = serve(&bus);
+
+        let client = Notifications::with_connection(bus.connect(), "Test App").unwrap();
+
+        let mut n = Notification::new("Summary", "Body");
+        n.urgency = Urgency::Critical;
+        n.actions = vec![("confirm".into(), "Detach".into()), ("cancel".into(), "Cancel".into())];
+        n.timeout = Some(Duration::ZERO);
+
+        let id = client.notify(0, &n).unwrap();
+        assert_eq!(id, 1);
+
+        assert_eq!(calls.recv_timeout(Duration::from_secs(5)).unwrap(), Call {
+            app_name: "Test App".into(),
+            replaces: 0,
+            summary: "Summary".into(),
+            body: "Body".into(),
+            actions: vec!["confirm".into(), "Detach".into(), "cancel".into(), "Cancel".into()],
+            urgency: Some(2),
+            timeout: 0,
+        });
+
+        // Replacing keeps the ID, the server default timeout is -1.
+        assert_eq!(client.notify(id, &Notification::new("Updated", "")).unwrap(), id);
+
+        let call = calls.recv_timeout(Duration::from_secs(5)).unwrap();
+        assert_eq!((call.replaces, call.urgency, call.timeout), (id, Some(1), -1));
+        assert!(call.actions.is_empty());
+
+        // Signals are broadcast by the server.
+        let server = bus.connect();
+
+        let msg = Message::new_signal(PATH, IFACE, "ActionInvoked").unwrap().append2(id, "confirm");
+        server.send(msg).unwrap();
+
+        let msg = Message::new_signal(PATH, IFACE, "NotificationClosed").unwrap().append2(id, 2u32);
+        server.send(msg).unwrap();
+
+        assert_eq!(wait_for_signals(&client, 2), vec![
+            Signal::ActionInvoked { id, action: "confirm".into() },
+            Signal::Closed { id },
+        ]);
+    }
+}
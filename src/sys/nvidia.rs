@@ -0,0 +1,92 @@
+use std::path::{Path, PathBuf};
+
+use crate::sys::pci::PciDevice;
+use crate::sys::{Error, Result};
+
+
+pub const DRIVER_NAME: &str = "nvidia";
+
+
+/// Runtime D3 report of the proprietary NVIDIA driver.
+///
+/// Parsed from `/proc/driver/nvidia/gpus/<bdf>/power`.
+#[derive(Debug, Clone, Default)]
+pub struct PowerReport {
+    pub runtime_d3_status: Option<String>,
+    pub video_memory: Option<String>,
+}
+
+impl PowerReport {
+    /// Load the report for the given device.
+    ///
+    /// Returns `None` if the device is not bound to the proprietary NVIDIA
+    /// driver or the driver does not provide a report for it.
+    pub fn load(device: &PciDevice) -> Result<Option<Self>> {
+        match device.driver() {
+            Some(driver) if driver == DRIVER_NAME => (),
+            _ => return Ok(None),
+        }
+
+        let path = proc_path(device.address(), "power");
+        if !path.is_file() {
+            return Ok(None);
+        }
+
+        let text = std::fs::read_to_string(&path)
+            .map_err(|source| Error::DeviceAccess { source, device: path })?;
+
+        Ok(Some(PowerReport::parse(&text)))
+    }
+
+    fn parse(text: &str) -> Self {
+        let mut report = PowerReport::default();
+
+        for line in text.lines() {
+            let (key, value) = match line.split_once(':') {
+                Some((key, value)) => (key.trim(), value.trim()),
+                None => continue,
+            };
+
+            if value.is_empty() {
+                continue;
+            }
+
+            match key {
+                "Runtime D3 status" => report.runtime_d3_status = Some(value.to_owned()),
+                "Video Memory"      => report.video_memory = Some(value.to_owned()),
+                _ => (),
+            }
+        }
+
+        report
+    }
+}
+
+
+/// Device node (`/dev/nvidiaN`) of the given device, if bound to the
+/// proprietary NVIDIA driver.
+pub fn device_node(device: &PciDevice) -> Result<Option<PathBuf>> {
+    match device.driver() {
+        Some(driver) if driver == DRIVER_NAME => (),
+        _ => return Ok(None),
+    }
+
+    let path = proc_path(device.address(), "information");
+    if !path.is_file() {
+        return Ok(None);
+    }
+
+    let text = std::fs::read_to_string(&path)
+        .map_err(|source| Error::DeviceAccess { source, device: path })?;
+
+    let minor = text.lines()
+        .filter_map(|line| line.split_once(':'))
+        .find(|(key, _)| key.trim() == "Device Minor")
+        .and_then(|(_, value)| value.trim().parse::<u32>().ok());
+
+    Ok(minor.map(|minor| PathBuf::from(format!("/dev/nvidia{minor}"))))
+}
+
+fn proc_path<P: AsRef<Path>>(address: P, file: &str) -> PathBuf {
+    Path::new("/proc/driver/nvidia/gpus").join(address).join(file)
+}
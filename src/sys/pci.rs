@@ -1,4 +1,6 @@
 use std::{convert::TryFrom, ffi::{OsStr, OsString}, str::FromStr};
+use std::path::PathBuf;
+use std::time::{Duration, Instant};
 
 use crate::sys::{Error, Result};
 
@@ -19,6 +21,8 @@ pub const VENDOR_ID_NVIDIA: u16 = 0x10de;
 
 pub const BASE_CLASS_DISPLAY: u8 = 0x03;
 
+const POWER_STATE_POLL_INTERVAL: Duration = Duration::from_millis(10);
+
 
 #[derive(Debug, Clone, Copy)]
 #[allow(unused)]
@@ -28,8 +32,18 @@ pub struct Class {
     pub iface: u8,
 }
 
+impl std::fmt::LowerHex for Class {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        if f.alternate() {
+            write!(f, "0x")?;
+        }
 
-#[derive(Debug, Clone, Copy)]
+        write!(f, "{:02x}{:02x}{:02x}", self.base, self.sub, self.iface)
+    }
+}
+
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum PowerState {
     Unknown,
     Error,
@@ -75,7 +89,7 @@ impl std::fmt::Display for PowerState {
 }
 
 
-#[derive(Debug, Clone, Copy)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum RuntimePowerManagement {
     On,
     Off
@@ -139,11 +153,177 @@ pub struct PciDevice {
 }
 
 impl PciDevice {
-    #[allow(unused)]
     pub fn base(&self) -> &udev::Device {
         &self.base
     }
 
+    pub fn address(&self) -> &OsStr {
+        self.base.sysname()
+    }
+
+    pub fn driver(&self) -> Option<&OsStr> {
+        self.base.driver()
+    }
+
+    /// PCI path tag of this device as used by udev and Mesa, e.g.
+    /// `pci-0000_01_00_0`.
+    pub fn path_tag(&self) -> String {
+        let address = self.address().to_string_lossy()
+            .replace([':', '.'], "_");
+
+        format!("pci-{address}")
+    }
+
+    /// Collect all functions in the same PCI slot as this device, including
+    /// the device itself, ordered by address.
+    pub fn slot_functions(&self) -> Result<Vec<PciDevice>> {
+        let address = self.address().to_string_lossy();
+        let slot = match address.rsplit_once('.') {
+            Some((slot, _)) => slot,
+            None => return Ok(vec![self.reload()?]),
+        };
+
+        let mut enumerator = udev::Enumerator::new()
+            .map_err(|source| Error::Io { source })?;
+
+        enumerator.match_subsystem("pci")
+            .map_err(|source| Error::Io { source })?;
+
+        enumerator.match_sysname(format!("{slot}.*"))
+            .map_err(|source| Error::Io { source })?;
+
+        let devices = enumerator.scan_devices()
+            .map_err(|source| Error::Io { source })?;
+
+        let mut functions = devices.map(PciDevice::try_from)
+            .collect::<SysFsResult<Vec<_>>>()
+            .map_err(|source| Error::SysFs { source })?;
+
+        functions.sort_by(|a, b| a.address().cmp(b.address()));
+        Ok(functions)
+    }
+
+    /// Generate a udev rule setting the runtime PM mode of this device.
+    pub fn runtime_pm_rule(&self, mode: RuntimePowerManagement) -> SysFsResult<String> {
+        let vendor = self.vendor_id()?;
+        let device = self.device_id()?;
+        let class = self.class()?;
+
+        Ok(format!(
+            "ACTION==\"add|bind\", SUBSYSTEM==\"pci\", ATTR{{vendor}}==\"{vendor:#06x}\", \
+             ATTR{{device}}==\"{device:#06x}\", ATTR{{class}}==\"{class:#x}\", \
+             TEST==\"power/control\", ATTR{{power/control}}=\"{}\"",
+            mode.as_sysfs()
+        ))
+    }
+
+    /// Re-read the device from sysfs.
+    ///
+    /// Attribute values are cached by udev, so this is required to observe
+    /// changes of e.g. the power state.
+    pub fn reload(&self) -> Result<Self> {
+        let base = udev::Device::from_syspath(self.base.syspath())
+            .map_err(|source| Error::Io { source })?;
+
+        Ok(PciDevice { base })
+    }
+
+    /// Wait until the device reaches the given power state.
+    ///
+    /// Returns `false` if the state has not been reached before the timeout.
+    pub fn wait_for_power_state(&self, state: PowerState, timeout: Duration) -> Result<bool> {
+        let start = Instant::now();
+
+        loop {
+            let current = self.reload()?
+                .get_power_state()
+                .map_err(|source| Error::SysFs { source })?;
+
+            if current == state {
+                return Ok(true);
+            }
+
+            if start.elapsed() >= timeout {
+                return Ok(false);
+            }
+
+            std::thread::sleep(POWER_STATE_POLL_INTERVAL);
+        }
+    }
+
+    /// Read from the start of the PCI configuration space of the device.
+    ///
+    /// This resumes the device if it is runtime-suspended.
+    pub fn read_config(&self, buf: &mut [u8]) -> Result<()> {
+        use std::io::Read;
+
+        let path = self.base.syspath().join("config");
+
+        std::fs::File::open(&path)
+            .and_then(|mut file| file.read_exact(buf))
+            .map_err(|source| Error::DeviceAccess { source, device: path })
+    }
+
+    /// The upstream PCI device, i.e. the bridge this device is connected to.
+    pub fn parent(&self) -> Option<PciDevice> {
+        self.base.parent().and_then(|p| PciDevice::try_from(p).ok())
+    }
+
+    /// Collect all device nodes (e.g. `/dev/dri/renderD128`) provided by
+    /// this device or its children.
+    pub fn device_nodes(&self) -> Result<Vec<PathBuf>> {
+        let mut enumerator = udev::Enumerator::new()
+            .map_err(|source| Error::Io { source })?;
+
+        enumerator.match_parent(&self.base)
+            .map_err(|source| Error::Io { source })?;
+
+        let nodes = enumerator.scan_devices()
+            .map_err(|source| Error::Io { source })?
+            .filter_map(|d| d.devnode().map(|n| n.to_owned()))
+            .collect();
+
+        Ok(nodes)
+    }
+
+    /// Names of DRM connectors of this device with a display connected.
+    pub fn connected_displays(&self) -> Result<Vec<String>> {
+        let mut enumerator = udev::Enumerator::new()
+            .map_err(|source| Error::Io { source })?;
+
+        enumerator.match_subsystem("drm")
+            .map_err(|source| Error::Io { source })?;
+
+        enumerator.match_parent(&self.base)
+            .map_err(|source| Error::Io { source })?;
+
+        let connectors = enumerator.scan_devices()
+            .map_err(|source| Error::Io { source })?
+            .filter(|d| d.attribute_value("status").is_some_and(|s| s == "connected"))
+            .map(|d| d.sysname().to_string_lossy().into_owned())
+            .collect();
+
+        Ok(connectors)
+    }
+
+    /// Look up the DRM render node (`renderD*`) provided by this device.
+    pub fn render_node(&self) -> Result<Option<udev::Device>> {
+        let mut enumerator = udev::Enumerator::new()
+            .map_err(|source| Error::Io { source })?;
+
+        enumerator.match_subsystem("drm")
+            .map_err(|source| Error::Io { source })?;
+
+        enumerator.match_parent(&self.base)
+            .map_err(|source| Error::Io { source })?;
+
+        let node = enumerator.scan_devices()
+            .map_err(|source| Error::Io { source })?
+            .find(|d| d.sysname().to_string_lossy().starts_with("renderD"));
+
+        Ok(node)
+    }
+
     pub fn vendor_id(&self) -> SysFsResult<u16> {
         let attribute = "vendor";
 
@@ -172,6 +352,34 @@ impl PciDevice {
             .map_err(|_| SysFsError::InvalidAttributeValue { attribute, value: id.into() })
     }
 
+    pub fn subsystem_vendor_id(&self) -> SysFsResult<u16> {
+        let attribute = "subsystem_vendor";
+
+        let id = self.base.attribute_value(attribute)
+            .ok_or(SysFsError::MissingAttribute { attribute })?;
+
+        let id = id.to_str()
+            .ok_or_else(|| SysFsError::InvalidAttributeValue { attribute, value: id.into() })?
+            .trim_start_matches("0x");
+
+        u16::from_str_radix(id, 16)
+            .map_err(|_| SysFsError::InvalidAttributeValue { attribute, value: id.into() })
+    }
+
+    pub fn subsystem_device_id(&self) -> SysFsResult<u16> {
+        let attribute = "subsystem_device";
+
+        let id = self.base.attribute_value(attribute)
+            .ok_or(SysFsError::MissingAttribute { attribute })?;
+
+        let id = id.to_str()
+            .ok_or_else(|| SysFsError::InvalidAttributeValue { attribute, value: id.into() })?
+            .trim_start_matches("0x");
+
+        u16::from_str_radix(id, 16)
+            .map_err(|_| SysFsError::InvalidAttributeValue { attribute, value: id.into() })
+    }
+
     pub fn class(&self) -> SysFsResult<Class> {
         let attribute = "class";
 
@@ -206,6 +414,48 @@ impl PciDevice {
             .ok_or(SysFsError::MissingAttribute { attribute })?)
     }
 
+    pub fn get_runtime_usage(&self) -> SysFsResult<u32> {
+        let attribute = "power/runtime_usage";
+
+        let value = self.base.attribute_value(attribute)
+            .ok_or(SysFsError::MissingAttribute { attribute })?;
+
+        value.to_str()
+            .and_then(|v| v.trim().parse().ok())
+            .ok_or_else(|| SysFsError::InvalidAttributeValue { attribute, value: value.into() })
+    }
+
+    /// Whether runtime PM has been disabled for this device by the kernel or
+    /// its driver, i.e. whether `power/runtime_enabled` reads `disabled`.
+    ///
+    /// The attribute reads `forbidden` if runtime PM has only been turned off
+    /// via `power/control`, which is not reported here.
+    pub fn get_runtime_disabled(&self) -> SysFsResult<bool> {
+        let attribute = "power/runtime_enabled";
+
+        let value = self.base.attribute_value(attribute)
+            .ok_or(SysFsError::MissingAttribute { attribute })?;
+
+        let value = value.to_str()
+            .ok_or_else(|| SysFsError::InvalidAttributeValue { attribute, value: value.into() })?;
+
+        // Either "disabled" or "disabled & forbidden".
+        Ok(value.trim().starts_with("disabled"))
+    }
+
+    pub fn get_d3cold_allowed(&self) -> SysFsResult<bool> {
+        let attribute = "d3cold_allowed";
+
+        let value = self.base.attribute_value(attribute)
+            .ok_or(SysFsError::MissingAttribute { attribute })?;
+
+        match value.to_str().map(|v| v.trim()) {
+            Some("1") => Ok(true),
+            Some("0") => Ok(false),
+            _ => Err(SysFsError::InvalidAttributeValue { attribute, value: value.into() }),
+        }
+    }
+
     pub fn set_runtime_pm(&mut self, state: RuntimePowerManagement) -> Result<()> {
         self.base.set_attribute_value("power/control", state.as_sysfs())
             .map_err(|source| {
@@ -0,0 +1,205 @@
+use std::path::{Path, PathBuf};
+
+use crate::sys::{Error, Result};
+
+
+pub const DEFAULT_PATHS: &[&str] = &[
+    "/usr/share/hwdata/pci.ids",
+    "/usr/share/misc/pci.ids",
+];
+
+
+/// Names resolved from the PCI ID database.
+#[derive(Debug, Clone, Default)]
+pub struct Names {
+    pub vendor: Option<String>,
+    pub device: Option<String>,
+    pub subsystem_vendor: Option<String>,
+    pub subsystem: Option<String>,
+}
+
+
+/// The system PCI ID database (`pci.ids`).
+pub struct Database {
+    path: PathBuf,
+}
+
+impl Database {
+    pub fn open() -> Result<Self> {
+        use std::io;
+
+        for path in DEFAULT_PATHS {
+            if Path::new(path).is_file() {
+                return Database::open_path(path);
+            }
+        }
+
+        Err(Error::DeviceAccess {
+            source: io::Error::new(io::ErrorKind::NotFound, "No PCI ID database found"),
+            device: PathBuf::from(DEFAULT_PATHS[0]),
+        })
+    }
+
+    pub fn open_path<P: AsRef<Path>>(path: P) -> Result<Self> {
+        use std::io;
+
+        if path.as_ref().is_file() {
+            Ok(Database { path: path.as_ref().to_owned() })
+        } else {
+            Err(Error::DeviceAccess {
+                source: io::Error::new(io::ErrorKind::NotFound, "PCI ID database not found"),
+                device: path.as_ref().to_owned(),
+            })
+        }
+    }
+
+    pub fn lookup(&self, vendor: u16, device: u16, subsystem: Option<(u16, u16)>) -> Result<Names> {
+        let text = std::fs::read(&self.path)
+            .map_err(|source| Error::DeviceAccess { source, device: self.path.clone() })?;
+
+        // The database is mostly ASCII but not guaranteed to be valid UTF-8.
+        let text = String::from_utf8_lossy(&text);
+
+        Ok(lookup(&text, vendor, device, subsystem))
+    }
+}
+
+
+fn lookup(text: &str, vendor: u16, device: u16, subsystem: Option<(u16, u16)>) -> Names {
+    let mut names = Names::default();
+    let mut in_vendor = false;
+    let mut in_device = false;
+
+    for line in text.lines() {
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+
+        // Device classes follow the vendor list, we are not interested in them.
+        if line.starts_with("C ") {
+            break;
+        }
+
+        if let Some(line) = line.strip_prefix("\t\t") {
+            if !in_device {
+                continue;
+            }
+
+            let (ids, name) = match split_entry(line) {
+                Some(entry) => entry,
+                None => continue,
+            };
+
+            let mut ids = ids.split_ascii_whitespace()
+                .map(|id| u16::from_str_radix(id, 16).ok());
+
+            if let (Some(Some(sv)), Some(Some(sd))) = (ids.next(), ids.next()) {
+                if Some((sv, sd)) == subsystem {
+                    names.subsystem = Some(name.to_owned());
+                }
+            }
+
+        } else if let Some(line) = line.strip_prefix('\t') {
+            if !in_vendor {
+                continue;
+            }
+
+            in_device = false;
+
+            if let Some((id, name)) = parse_entry(line) {
+                if id == device {
+                    names.device = Some(name.to_owned());
+                    in_device = true;
+                }
+            }
+
+        } else if let Some((id, name)) = parse_entry(line) {
+            in_vendor = id == vendor;
+            in_device = false;
+
+            if id == vendor {
+                names.vendor = Some(name.to_owned());
+            }
+            if Some(id) == subsystem.map(|(sv, _)| sv) {
+                names.subsystem_vendor = Some(name.to_owned());
+            }
+        }
+    }
+
+    names
+}
+
+fn split_entry(line: &str) -> Option<(&str, &str)> {
+    line.split_once("  ").map(|(ids, name)| (ids.trim(), name.trim()))
+}
+
+fn parse_entry(line: &str) -> Option<(u16, &str)> {
+    let (id, name) = split_entry(line)?;
+    let id = u16::from_str_radix(id, 16).ok()?;
+
+    Some((id, name))
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const DATABASE: &str = "\
+# Comment
+10de  NVIDIA Corporation
+\t1c8f  GP107M [GeForce GTX 1050 Ti Mobile]
+\t1d10  GP108M [GeForce MX150]
+\t1e90  TU106M [GeForce RTX 2060 Mobile]
+\t\t1414 0037  Surface Book 3
+\t\t1414 0038  Other
+1414  Microsoft Corporation
+8086  Intel Corporation
+\t1e90  Not this one
+
+C 03  Display controller
+\t00  VGA compatible controller
+";
+
+    #[test]
+    fn lookup_device() {
+        let names = lookup(DATABASE, 0x10de, 0x1d10, None);
+
+        assert_eq!(names.vendor.as_deref(), Some("NVIDIA Corporation"));
+        assert_eq!(names.device.as_deref(), Some("GP108M [GeForce MX150]"));
+        assert_eq!(names.subsystem_vendor, None);
+        assert_eq!(names.subsystem, None);
+    }
+
+    #[test]
+    fn lookup_subsystem() {
+        let names = lookup(DATABASE, 0x10de, 0x1e90, Some((0x1414, 0x0037)));
+
+        assert_eq!(names.device.as_deref(), Some("TU106M [GeForce RTX 2060 Mobile]"));
+        assert_eq!(names.subsystem_vendor.as_deref(), Some("Microsoft Corporation"));
+        assert_eq!(names.subsystem.as_deref(), Some("Surface Book 3"));
+    }
+
+    #[test]
+    fn lookup_unknown() {
+        let names = lookup(DATABASE, 0x10de, 0xffff, Some((0x1414, 0xffff)));
+
+        assert_eq!(names.vendor.as_deref(), Some("NVIDIA Corporation"));
+        assert_eq!(names.device, None);
+        assert_eq!(names.subsystem, None);
+
+        let names = lookup(DATABASE, 0xffff, 0x1e90, None);
+
+        assert_eq!(names.vendor, None);
+        assert_eq!(names.device, None);
+    }
+
+    #[test]
+    fn stops_at_classes() {
+        // Class entries use the same layout and must not be mistaken for
+        // vendors.
+        let names = lookup(DATABASE, 0x0003, 0x0000, None);
+
+        assert_eq!(names.vendor, None);
+    }
+}
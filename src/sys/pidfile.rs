@@ -0,0 +1,112 @@
+use std::convert::TryFrom;
+use std::io::{Read, Seek, Write};
+use std::path::PathBuf;
+
+use nix::errno::Errno;
+use nix::fcntl::{Flock, FlockArg};
+use nix::unistd::Pid;
+
+use crate::sys::{Error, Result};
+
+
+/// A file listing the processes holding a shared resource, used to
+/// reference-count it across processes.
+///
+/// Entries of processes that have exited without removing themselves are
+/// dropped automatically.
+pub struct PidFile {
+    path: PathBuf,
+}
+
+impl PidFile {
+    pub fn new<P: Into<PathBuf>>(path: P) -> Self {
+        PidFile { path: path.into() }
+    }
+
+    /// Run `f` on the list of live processes in the file while holding an
+    /// exclusive lock on it. Changes to the list are written back.
+    pub fn update<F, R>(&self, f: F) -> Result<R>
+    where
+        F: FnOnce(&mut Vec<u32>) -> R,
+    {
+        let access = |source| Error::DeviceAccess { source, device: self.path.clone() };
+
+        if let Some(dir) = self.path.parent() {
+            std::fs::create_dir_all(dir).map_err(access)?;
+        }
+
+        let file = std::fs::OpenOptions::new()
+            .read(true)
+            .write(true)
+            .create(true)
+            .truncate(false)
+            .open(&self.path)
+            .map_err(access)?;
+
+        let mut file = Flock::lock(file, FlockArg::LockExclusive)
+            .map_err(|(_, errno)| access(errno.into()))?;
+
+        let mut text = String::new();
+        file.read_to_string(&mut text).map_err(access)?;
+
+        let mut pids: Vec<u32> = text.lines()
+            .filter_map(|l| l.trim().parse().ok())
+            .filter(|pid| is_alive(*pid))
+            .collect();
+
+        let result = f(&mut pids);
+
+        let text: String = pids.iter().map(|pid| format!("{pid}\n")).collect();
+
+        file.set_len(0).map_err(access)?;
+        file.rewind().map_err(access)?;
+        file.write_all(text.as_bytes()).map_err(access)?;
+
+        Ok(result)
+    }
+}
+
+
+/// Check whether the process with the given PID exists.
+pub fn is_alive(pid: u32) -> bool {
+    let pid = match i32::try_from(pid) {
+        Ok(pid) => Pid::from_raw(pid),
+        Err(_) => return false,
+    };
+
+    matches!(nix::sys::signal::kill(pid, None), Ok(()) | Err(Errno::EPERM))
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn update() {
+        let dir = std::env::temp_dir().join(format!("surface-test-pidfile-{}", std::process::id()));
+        let file = PidFile::new(dir.join("pids"));
+
+        let mut child = std::process::Command::new("sleep").arg("60").spawn().unwrap();
+        let (me, other) = (std::process::id(), child.id());
+
+        file.update(|pids| pids.extend([me, other])).unwrap();
+        assert_eq!(file.update(|pids| pids.clone()).unwrap(), vec![me, other]);
+
+        let _ = child.kill();
+        let _ = child.wait();
+
+        // Exited processes are dropped, changes are written back.
+        file.update(|pids| pids.retain(|p| *p != me)).unwrap();
+        let pids = file.update(|pids| pids.clone()).unwrap();
+        std::fs::remove_dir_all(&dir).unwrap();
+
+        assert!(pids.is_empty(), "{:?}", pids);
+    }
+
+    #[test]
+    fn alive() {
+        assert!(is_alive(std::process::id()));
+        assert!(!is_alive(u32::MAX));
+    }
+}
@@ -0,0 +1,65 @@
+use std::path::{Path, PathBuf};
+
+use crate::sys::{Error, Result};
+
+
+/// A process holding an open file.
+#[derive(Debug, Clone)]
+pub struct Process {
+    pub pid: u32,
+    pub comm: String,
+}
+
+impl std::fmt::Display for Process {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{} ({})", self.comm, self.pid)
+    }
+}
+
+
+/// Find all processes holding any of the given files open.
+///
+/// Processes we are not allowed to inspect are silently skipped, so this may
+/// be incomplete when not run as root.
+pub fn processes_using<P: AsRef<Path>>(paths: &[P]) -> Result<Vec<Process>> {
+    let mut found = Vec::new();
+
+    if paths.is_empty() {
+        return Ok(found);
+    }
+
+    let entries = std::fs::read_dir("/proc")
+        .map_err(|source| Error::DeviceAccess { source, device: PathBuf::from("/proc") })?;
+
+    for entry in entries {
+        let entry = entry.map_err(|source| Error::Io { source })?;
+
+        let pid: u32 = match entry.file_name().to_str().and_then(|n| n.parse().ok()) {
+            Some(pid) => pid,
+            None => continue,
+        };
+
+        let fds = match std::fs::read_dir(entry.path().join("fd")) {
+            Ok(fds) => fds,
+            Err(_) => continue,
+        };
+
+        for fd in fds.flatten() {
+            let target = match std::fs::read_link(fd.path()) {
+                Ok(target) => target,
+                Err(_) => continue,
+            };
+
+            if paths.iter().any(|p| p.as_ref() == target) {
+                let comm = std::fs::read_to_string(entry.path().join("comm"))
+                    .map(|c| c.trim().to_owned())
+                    .unwrap_or_default();
+
+                found.push(Process { pid, comm });
+                break;
+            }
+        }
+    }
+
+    Ok(found)
+}
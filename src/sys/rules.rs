@@ -0,0 +1,65 @@
+use std::path::{Path, PathBuf};
+
+use crate::sys::{Error, Result};
+
+
+pub const RULES_DIR: &str = "/etc/udev/rules.d";
+
+
+/// A udev rule file generated by us.
+pub struct RuleFile {
+    path: PathBuf,
+}
+
+impl RuleFile {
+    pub fn new(name: &str) -> Self {
+        RuleFile {
+            path: Path::new(RULES_DIR).join(name),
+        }
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    pub fn exists(&self) -> bool {
+        self.path.is_file()
+    }
+
+    pub fn write(&self, rules: &[String]) -> Result<()> {
+        let mut content = String::from("# Generated by surface-control, do not edit.\n");
+        for rule in rules {
+            content += rule;
+            content += "\n";
+        }
+
+        std::fs::write(&self.path, content)
+            .map_err(|source| Error::DeviceAccess { source, device: self.path.clone() })
+    }
+
+    /// Remove the rule file. Returns `false` if it did not exist.
+    pub fn remove(&self) -> Result<bool> {
+        match std::fs::remove_file(&self.path) {
+            Ok(()) => Ok(true),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(false),
+            Err(source) => Err(Error::DeviceAccess { source, device: self.path.clone() }),
+        }
+    }
+}
+
+
+/// Ask udev to reload its rules.
+pub fn reload() -> Result<()> {
+    let command = "udevadm control --reload-rules";
+
+    let status = std::process::Command::new("udevadm")
+        .args(["control", "--reload-rules"])
+        .status()
+        .map_err(|source| Error::Io { source })?;
+
+    if !status.success() {
+        return Err(Error::Command { command, status });
+    }
+
+    Ok(())
+}
@@ -0,0 +1,77 @@
+use std::path::Path;
+
+use crate::sys::pci::PciDevice;
+use crate::sys::{Error, Result};
+
+
+/// Sensor readings of a GPU, as far as exposed by its driver.
+///
+/// Note that reading any of these may wake the device, so callers should
+/// ensure that it is active (i.e. in D0) before loading them.
+#[derive(Debug, Clone, Default)]
+pub struct Sensors {
+    /// Temperature in degrees Celsius.
+    pub temperature: Option<f64>,
+
+    /// Fan speed in RPM.
+    pub fan: Option<u64>,
+
+    /// Power draw in Watts.
+    pub power: Option<f64>,
+
+    /// Used VRAM in bytes.
+    pub vram_used: Option<u64>,
+
+    /// Total VRAM in bytes.
+    pub vram_total: Option<u64>,
+}
+
+impl Sensors {
+    pub fn load(device: &PciDevice) -> Result<Self> {
+        let mut sensors = Sensors::default();
+
+        if let Some(hwmon) = find_hwmon(device)? {
+            let path = hwmon.syspath();
+
+            sensors.temperature = read_u64(path.join("temp1_input"))
+                .map(|t| t as f64 / 1000.0);
+
+            sensors.fan = read_u64(path.join("fan1_input"));
+
+            sensors.power = read_u64(path.join("power1_average"))
+                .or_else(|| read_u64(path.join("power1_input")))
+                .map(|p| p as f64 / 1_000_000.0);
+        }
+
+        let path = device.base().syspath();
+        sensors.vram_used = read_u64(path.join("mem_info_vram_used"));
+        sensors.vram_total = read_u64(path.join("mem_info_vram_total"));
+
+        Ok(sensors)
+    }
+}
+
+
+fn find_hwmon(device: &PciDevice) -> Result<Option<udev::Device>> {
+    let mut enumerator = udev::Enumerator::new()
+        .map_err(|source| Error::Io { source })?;
+
+    enumerator.match_subsystem("hwmon")
+        .map_err(|source| Error::Io { source })?;
+
+    enumerator.match_parent(device.base())
+        .map_err(|source| Error::Io { source })?;
+
+    let hwmon = enumerator.scan_devices()
+        .map_err(|source| Error::Io { source })?
+        .next();
+
+    Ok(hwmon)
+}
+
+fn read_u64<P: AsRef<Path>>(path: P) -> Option<u64> {
+    std::fs::read_to_string(path).ok()?
+        .trim()
+        .parse()
+        .ok()
+}
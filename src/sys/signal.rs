@@ -0,0 +1,51 @@
+use std::convert::TryFrom;
+use std::sync::atomic::{AtomicI32, Ordering};
+
+use nix::sys::signal::{SaFlags, SigAction, SigHandler, SigSet, Signal};
+
+use crate::sys::{Error, Result};
+
+
+/// Signals that normally terminate the process and that we want to handle
+/// gracefully.
+const TERMINATION_SIGNALS: [Signal; 3] = [Signal::SIGINT, Signal::SIGTERM, Signal::SIGHUP];
+
+static RECEIVED: AtomicI32 = AtomicI32::new(0);
+
+
+extern "C" fn record_signal(signal: nix::libc::c_int) {
+    RECEIVED.store(signal, Ordering::SeqCst);
+}
+
+
+/// Catch SIGINT, SIGTERM, and SIGHUP, recording them instead of terminating
+/// the process. Check for them via [`received`].
+pub fn catch_termination() -> Result<()> {
+    let action = SigAction::new(SigHandler::Handler(record_signal), SaFlags::SA_RESTART, SigSet::empty());
+
+    for signal in TERMINATION_SIGNALS {
+        // SAFETY: The handler only stores to an atomic, which is async-signal-safe.
+        unsafe { nix::sys::signal::sigaction(signal, &action) }
+            .map_err(|errno| Error::Io { source: errno.into() })?;
+    }
+
+    Ok(())
+}
+
+/// The most recent termination signal caught since [`catch_termination`]
+/// has been called, if any.
+pub fn received() -> Option<Signal> {
+    match RECEIVED.load(Ordering::SeqCst) {
+        0      => None,
+        signal => Signal::try_from(signal).ok(),
+    }
+}
+
+/// Like [`received`], but also resets the recorded signal so that each
+/// signal is only reported once.
+pub fn take() -> Option<Signal> {
+    match RECEIVED.swap(0, Ordering::SeqCst) {
+        0      => None,
+        signal => Signal::try_from(signal).ok(),
+    }
+}
@@ -0,0 +1,145 @@
+use std::path::{Path, PathBuf};
+use std::process::Stdio;
+
+use crate::sys::mounts::Mount;
+use crate::sys::{Error, Result};
+
+
+/// A whole-disk block device (e.g. `sdb`) and its partitions.
+pub struct Disk {
+    base: udev::Device,
+    partitions: Vec<udev::Device>,
+}
+
+impl Disk {
+    /// Group the given block devices into disks.
+    pub fn from_devices(devices: Vec<udev::Device>) -> Vec<Disk> {
+        let (disks, partitions): (Vec<_>, Vec<_>) = devices.into_iter()
+            .partition(|d| d.devtype().is_some_and(|t| t == "disk"));
+
+        disks.into_iter()
+            .map(|base| {
+                let partitions = partitions.iter()
+                    .filter(|p| p.parent().is_some_and(|d| d.syspath() == base.syspath()))
+                    .cloned()
+                    .collect();
+
+                Disk { base, partitions }
+            })
+            .collect()
+    }
+
+    pub fn name(&self) -> String {
+        self.base.sysname().to_string_lossy().into_owned()
+    }
+
+    pub fn node(&self) -> Option<&Path> {
+        self.base.devnode()
+    }
+
+    /// The disk itself followed by all of its partitions.
+    pub fn block_devices(&self) -> impl Iterator<Item=&udev::Device> {
+        std::iter::once(&self.base).chain(self.partitions.iter())
+    }
+
+    /// Mounts of the disk or any of its partitions, with the node of the
+    /// mounted block device.
+    pub fn mounts(&self) -> Result<Vec<(PathBuf, Mount)>> {
+        let mut mounts = Vec::new();
+
+        for block in self.block_devices() {
+            let (dev, node) = match (block.attribute_value("dev"), block.devnode()) {
+                (Some(dev), Some(node)) => (dev.to_string_lossy(), node),
+                _ => continue,
+            };
+
+            for mount in crate::sys::mounts::mounts_of(&dev)? {
+                mounts.push((node.to_owned(), mount));
+            }
+        }
+
+        Ok(mounts)
+    }
+
+    /// Names of devices stacked on top of the disk or its partitions (e.g.
+    /// device-mapper or RAID devices), which keep it in use.
+    pub fn holders(&self) -> Vec<String> {
+        let mut holders = Vec::new();
+
+        for block in self.block_devices() {
+            let entries = match std::fs::read_dir(block.syspath().join("holders")) {
+                Ok(entries) => entries,
+                Err(_) => continue,
+            };
+
+            for entry in entries.flatten() {
+                holders.push(entry.file_name().to_string_lossy().into_owned());
+            }
+        }
+
+        holders
+    }
+
+    /// Power off the drive.
+    ///
+    /// Uses udisks if available, which also powers down the USB port.
+    /// Otherwise, the device is only removed from the SCSI layer.
+    pub fn power_off(&self, udisks: bool) -> Result<()> {
+        if let (true, Some(node)) = (udisks, self.node()) {
+            return run("udisksctl power-off", std::process::Command::new("udisksctl")
+                .arg("power-off")
+                .arg("--no-user-interaction")
+                .arg("-b")
+                .arg(node));
+        }
+
+        let path = self.base.syspath().join("device").join("delete");
+
+        std::fs::write(&path, "1")
+            .map_err(|source| Error::DeviceAccess { source, device: path })
+    }
+}
+
+
+/// Whether the udisks command line client is available.
+pub fn udisks_available() -> bool {
+    let path = match std::env::var_os("PATH") {
+        Some(path) => path,
+        None => return false,
+    };
+
+    std::env::split_paths(&path).any(|dir| dir.join("udisksctl").is_file())
+}
+
+/// Unmount the given mount of the block device `node`.
+///
+/// Tries udisks first, if requested, falling back to umount.
+pub fn unmount(node: &Path, mount: &Mount, udisks: bool) -> Result<()> {
+    if udisks {
+        let result = run("udisksctl unmount", std::process::Command::new("udisksctl")
+            .arg("unmount")
+            .arg("--no-user-interaction")
+            .arg("-b")
+            .arg(node));
+
+        if result.is_ok() {
+            return Ok(());
+        }
+    }
+
+    run("umount", std::process::Command::new("umount")
+        .arg(&mount.mountpoint))
+}
+
+
+fn run(command: &'static str, cmd: &mut std::process::Command) -> Result<()> {
+    let status = cmd.stdout(Stdio::null())
+        .status()
+        .map_err(|source| Error::Io { source })?;
+
+    if !status.success() {
+        return Err(Error::Command { command, status });
+    }
+
+    Ok(())
+}
@@ -0,0 +1,56 @@
+//! Private D-Bus daemon for tests.
+
+use std::io::{BufRead, BufReader};
+use std::process::{Child, Command, Stdio};
+
+use dbus::blocking::Connection;
+use dbus::channel::Channel;
+
+
+/// A private `dbus-daemon`, killed on drop.
+pub struct TestBus {
+    daemon: Child,
+    address: String,
+}
+
+impl TestBus {
+    /// Start a new daemon. Returns `None` if `dbus-daemon` is not available,
+    /// in which case the calling test should be skipped.
+    pub fn spawn() -> Option<Self> {
+        let mut daemon = Command::new("dbus-daemon")
+            .args(["--session", "--nofork", "--nopidfile", "--print-address=1"])
+            .stdin(Stdio::null())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::null())
+            .spawn()
+            .ok()?;
+
+        let mut address = String::new();
+        let stdout = daemon.stdout.take().unwrap();
+        BufReader::new(stdout).read_line(&mut address).ok()?;
+
+        let address = address.trim().to_owned();
+        if address.is_empty() {
+            let _ = daemon.kill();
+            let _ = daemon.wait();
+            return None;
+        }
+
+        Some(TestBus { daemon, address })
+    }
+
+    /// Open a new connection to the bus.
+    pub fn connect(&self) -> Connection {
+        let mut channel = Channel::open_private(&self.address).unwrap();
+        channel.register().unwrap();
+
+        Connection::from(channel)
+    }
+}
+
+impl Drop for TestBus {
+    fn drop(&mut self) {
+        let _ = self.daemon.kill();
+        let _ = self.daemon.wait();
+    }
+}